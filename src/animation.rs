@@ -1,57 +1,182 @@
-use crate::config::CONFIG;
-use crate::enemy::Enemy;
-use crate::player::Player;
+use crate::collision::Damaged;
+use crate::sprites::{AnimationClip, LoopMode};
 use crate::state::GameState;
-use crate::util::get_sprite_index;
 use bevy::prelude::*;
 
-#[derive(Component, Deref, DerefMut)]
-pub struct AnimationTimer(pub Timer);
+/// Movement slower than this, in pixels per second, counts as standing still.
+const IDLE_THRESHOLD: f32 = 3.0;
+
+/// Steps a sprite through the frames of one clip from the sprite manifest.
+#[derive(Component, Clone)]
+pub struct SpriteAnimation {
+    first_index: usize,
+    frames: usize,
+    mode: LoopMode,
+    frame: usize,
+    played_out: bool,
+    timer: Timer,
+}
+
+impl SpriteAnimation {
+    pub fn new(first_index: usize, clip: &AnimationClip) -> Self {
+        Self {
+            first_index,
+            frames: clip.frames as usize,
+            mode: clip.mode,
+            frame: 0,
+            played_out: false,
+            timer: Timer::from_seconds(1.0 / clip.fps, TimerMode::Repeating),
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.first_index + self.frame
+    }
+
+    /// Whether a clip that plays once has shown its last frame for a full frame time.
+    pub fn finished(&self) -> bool {
+        self.played_out
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Run,
+    Hurt,
+    Die,
+}
+
+impl AnimationState {
+    pub const ALL: [AnimationState; 4] = [
+        AnimationState::Idle,
+        AnimationState::Run,
+        AnimationState::Hurt,
+        AnimationState::Die,
+    ];
+
+    /// Suffix of the manifest clip played in this state, e.g. `player_run`.
+    pub fn clip_suffix(self) -> &'static str {
+        match self {
+            AnimationState::Idle => "idle",
+            AnimationState::Run => "run",
+            AnimationState::Hurt => "hurt",
+            AnimationState::Die => "die",
+        }
+    }
+}
+
+/// Switches a [`SpriteAnimation`] between clips as the entity moves, gets hit and dies.
+#[derive(Component)]
+pub struct AnimationController {
+    state: AnimationState,
+    clips: [SpriteAnimation; 4],
+    last_position: Option<Vec3>,
+}
+
+impl AnimationController {
+    /// Takes one clip per state, in the order of [`AnimationState::ALL`].
+    pub fn new(clips: [SpriteAnimation; 4]) -> Self {
+        Self {
+            state: AnimationState::Idle,
+            clips,
+            last_position: None,
+        }
+    }
+
+    pub fn state(&self) -> AnimationState {
+        self.state
+    }
+
+    pub fn clip(&self, state: AnimationState) -> &SpriteAnimation {
+        &self.clips[state as usize]
+    }
+
+    /// Restarts the clip for `state`, unless the entity is already dying.
+    pub fn play(&mut self, state: AnimationState, animation: &mut SpriteAnimation) {
+        if self.state == AnimationState::Die {
+            return;
+        }
+        self.state = state;
+        *animation = self.clip(state).clone();
+    }
+}
 
 pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.add_observer(play_hurt_animation).add_systems(
             Update,
-            (animation_timer_tick, animate_player, animate_enemy)
-                .run_if(in_state(GameState::Gaming)),
+            (update_animation_state, animate_sprites)
+                .chain()
+                .run_if(in_state(GameState::Gaming).or(in_state(GameState::Dying))),
         );
     }
 }
 
-fn animation_timer_tick(
+fn update_animation_state(
     time: Res<Time>,
-    mut timer_query: Query<&mut AnimationTimer, With<AnimationTimer>>,
+    mut sprite_query: Query<(
+        &Transform,
+        &mut AnimationController,
+        &mut SpriteAnimation,
+        &mut Sprite,
+    )>,
 ) {
-    for mut timer in timer_query.iter_mut() {
-        timer.tick(time.delta());
-    }
-}
-
-fn animate_player(mut player_query: Query<(&mut Sprite, &mut AnimationTimer), With<Player>>) {
-    if player_query.is_empty() {
+    // nothing moves while the game is paused
+    if time.delta_secs() == 0.0 {
         return;
     }
 
-    for (mut sprite, timer) in player_query.iter_mut() {
-        if timer.just_finished() {
-            let texture_atlas = sprite.texture_atlas.as_mut().unwrap();
-            texture_atlas.index = (texture_atlas.index + 1) % 6;
+    for (transform, mut controller, mut animation, mut sprite) in sprite_query.iter_mut() {
+        let velocity = controller
+            .last_position
+            .map_or(Vec3::ZERO, |last_position| {
+                (transform.translation - last_position) / time.delta_secs()
+            });
+        controller.last_position = Some(transform.translation);
+
+        // the sheet draws everyone facing left
+        if velocity.x.abs() > IDLE_THRESHOLD {
+            sprite.flip_x = velocity.x > 0.0;
+        }
+
+        let next = match controller.state {
+            AnimationState::Die => continue,
+            AnimationState::Hurt if !animation.finished() => continue,
+            _ if velocity.truncate().length() > IDLE_THRESHOLD => AnimationState::Run,
+            _ => AnimationState::Idle,
+        };
+        if next != controller.state {
+            controller.play(next, &mut animation);
         }
     }
 }
 
-fn animate_enemy(mut enemy_query: Query<(&mut Sprite, &mut AnimationTimer), With<Enemy>>) {
-    if enemy_query.is_empty() {
-        return;
+fn play_hurt_animation(
+    trigger: Trigger<Damaged>,
+    mut sprite_query: Query<(&mut AnimationController, &mut SpriteAnimation)>,
+) {
+    if let Ok((mut controller, mut animation)) = sprite_query.get_mut(trigger.entity()) {
+        controller.play(AnimationState::Hurt, &mut animation);
     }
+}
+
+fn animate_sprites(time: Res<Time>, mut sprite_query: Query<(&mut Sprite, &mut SpriteAnimation)>) {
+    for (mut sprite, mut animation) in sprite_query.iter_mut() {
+        animation.timer.tick(time.delta());
+        for _ in 0..animation.timer.times_finished_this_tick() {
+            match animation.mode {
+                LoopMode::Loop => animation.frame = (animation.frame + 1) % animation.frames,
+                LoopMode::Once if animation.frame + 1 < animation.frames => animation.frame += 1,
+                LoopMode::Once => animation.played_out = true,
+            }
+        }
 
-    for (mut sprite, timer) in enemy_query.iter_mut() {
-        if timer.just_finished() {
-            let texture_atlas = sprite.texture_atlas.as_mut().unwrap();
-            texture_atlas.index = get_sprite_index(3, 0)
-                + ((texture_atlas.index + 1) % (CONFIG.sprite.spritesheet_width as usize)) % 6;
+        if let Some(texture_atlas) = sprite.texture_atlas.as_mut() {
+            texture_atlas.index = animation.index();
         }
     }
 }
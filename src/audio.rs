@@ -0,0 +1,216 @@
+use crate::collision::Damaged;
+use crate::enemy::Enemy;
+use crate::player::Player;
+use crate::settings::{Settings, VolumeBus};
+use crate::state::GameState;
+use bevy::asset::UntypedAssetId;
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum SoundEffect {
+    Shoot,
+    EnemyHit,
+    EnemyDeath,
+    XpPickup,
+    LevelUp,
+    PlayerHurt,
+}
+
+impl SoundEffect {
+    pub const ALL: [SoundEffect; 6] = [
+        SoundEffect::Shoot,
+        SoundEffect::EnemyHit,
+        SoundEffect::EnemyDeath,
+        SoundEffect::XpPickup,
+        SoundEffect::LevelUp,
+        SoundEffect::PlayerHurt,
+    ];
+
+    fn path(self) -> &'static str {
+        match self {
+            SoundEffect::Shoot => "audio/shoot.wav",
+            SoundEffect::EnemyHit => "audio/enemy_hit.wav",
+            SoundEffect::EnemyDeath => "audio/enemy_death.wav",
+            SoundEffect::XpPickup => "audio/xp_pickup.wav",
+            SoundEffect::LevelUp => "audio/level_up.wav",
+            SoundEffect::PlayerHurt => "audio/player_hurt.wav",
+        }
+    }
+
+    /// Seconds before the same sound can play again, so a whole wave dying at once is one sound.
+    fn min_interval(self) -> f32 {
+        match self {
+            SoundEffect::Shoot | SoundEffect::EnemyHit | SoundEffect::XpPickup => 0.05,
+            SoundEffect::EnemyDeath => 0.08,
+            SoundEffect::PlayerHurt => 0.2,
+            SoundEffect::LevelUp => 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+enum MusicTrack {
+    Menu,
+    Run,
+    GameOver,
+}
+
+impl MusicTrack {
+    const ALL: [MusicTrack; 3] = [MusicTrack::Menu, MusicTrack::Run, MusicTrack::GameOver];
+
+    fn path(self) -> &'static str {
+        match self {
+            MusicTrack::Menu => "audio/music_menu.wav",
+            MusicTrack::Run => "audio/music_run.wav",
+            MusicTrack::GameOver => "audio/music_game_over.wav",
+        }
+    }
+
+    /// The track for a state, or `None` to keep whatever is already playing.
+    fn for_state(state: GameState) -> Option<Self> {
+        match state {
+            GameState::Loading | GameState::Settings { .. } => None,
+            GameState::MainMenu
+            | GameState::CharacterSelect
+            | GameState::Shop
+            | GameState::HighScores => Some(MusicTrack::Menu),
+            GameState::Initializing | GameState::Gaming | GameState::Paused | GameState::Dying => {
+                Some(MusicTrack::Run)
+            }
+            GameState::GameOver => Some(MusicTrack::GameOver),
+        }
+    }
+}
+
+/// Triggered to play a sound effect, which is dropped if the same one played too recently.
+#[derive(Event, Clone, Copy)]
+pub struct PlaySound(pub SoundEffect);
+
+/// Every sound effect and music track, loaded alongside the spritesheet.
+#[derive(Resource)]
+pub struct AudioAssets {
+    effects: HashMap<SoundEffect, Handle<AudioSource>>,
+    music: HashMap<MusicTrack, Handle<AudioSource>>,
+}
+
+impl AudioAssets {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        Self {
+            effects: SoundEffect::ALL
+                .into_iter()
+                .map(|effect| (effect, asset_server.load(effect.path())))
+                .collect(),
+            music: MusicTrack::ALL
+                .into_iter()
+                .map(|track| (track, asset_server.load(track.path())))
+                .collect(),
+        }
+    }
+
+    pub fn handles(&self) -> impl Iterator<Item = UntypedAssetId> + '_ {
+        self.effects
+            .values()
+            .chain(self.music.values())
+            .map(|handle| handle.id().untyped())
+    }
+}
+
+/// When each sound effect last played, in real time so menus and pauses don't matter.
+#[derive(Resource, Default)]
+struct SoundCooldowns(HashMap<SoundEffect, f32>);
+
+#[derive(Component)]
+struct Music(MusicTrack);
+
+pub struct AudioPlugin;
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SoundCooldowns>()
+            .add_observer(play_sound)
+            .add_observer(play_damage_sounds)
+            .add_systems(
+                Update,
+                (
+                    switch_music.run_if(state_changed::<GameState>),
+                    update_music_volume.run_if(resource_changed::<Settings>),
+                )
+                    .run_if(resource_exists::<AudioAssets>),
+            );
+    }
+}
+
+fn play_sound(
+    trigger: Trigger<PlaySound>,
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    settings: Res<Settings>,
+    audio_assets: Res<AudioAssets>,
+    mut cooldowns: ResMut<SoundCooldowns>,
+) {
+    let effect = trigger.event().0;
+    let now = time.elapsed_secs();
+    if cooldowns
+        .0
+        .get(&effect)
+        .is_some_and(|last_played| now - last_played < effect.min_interval())
+    {
+        return;
+    }
+    cooldowns.0.insert(effect, now);
+
+    let volume = settings.effective_volume(VolumeBus::Sfx);
+    if volume <= 0.0 {
+        return;
+    }
+    commands.spawn((
+        AudioPlayer(audio_assets.effects[&effect].clone()),
+        PlaybackSettings::DESPAWN.with_volume(Volume::new(volume)),
+    ));
+}
+
+fn play_damage_sounds(
+    trigger: Trigger<Damaged>,
+    mut commands: Commands,
+    enemy_query: Query<(), With<Enemy>>,
+    player_query: Query<(), With<Player>>,
+) {
+    if enemy_query.contains(trigger.entity()) {
+        commands.trigger(PlaySound(SoundEffect::EnemyHit));
+    } else if player_query.contains(trigger.entity()) {
+        commands.trigger(PlaySound(SoundEffect::PlayerHurt));
+    }
+}
+
+fn switch_music(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    settings: Res<Settings>,
+    audio_assets: Res<AudioAssets>,
+    music_query: Query<(&Music, Entity)>,
+) {
+    let Some(track) = MusicTrack::for_state(*state.get()) else {
+        return;
+    };
+    if music_query.iter().any(|(music, _)| music.0 == track) {
+        return;
+    }
+
+    for (_, entity) in music_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.spawn((
+        Music(track),
+        AudioPlayer(audio_assets.music[&track].clone()),
+        PlaybackSettings::LOOP
+            .with_volume(Volume::new(settings.effective_volume(VolumeBus::Music))),
+    ));
+}
+
+fn update_music_volume(settings: Res<Settings>, sink_query: Query<&AudioSink, With<Music>>) {
+    for sink in sink_query.iter() {
+        sink.set_volume(settings.effective_volume(VolumeBus::Music));
+    }
+}
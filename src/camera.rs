@@ -1,39 +1,143 @@
+use crate::collision::Damaged;
+use crate::config::Config;
 use crate::player::Player;
+use crate::settings::Settings;
 use crate::state::GameState;
+use crate::weapon::Aim;
 use bevy::app::{App, Plugin, Update};
-use bevy::math::vec3;
-use bevy::prelude::{
-    in_state, Camera, Camera2d, Commands, IntoSystemConfigs, OnEnter, Query, Transform, With,
-    Without,
-};
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use rand::Rng;
+
+/// Triggered to shake the camera, with `0.0..=1.0` trauma added to what is already there.
+#[derive(Event, Clone, Copy)]
+pub struct ShakeCamera(pub f32);
+
+/// Where the camera is heading, kept apart from the transform so shake never builds up.
+#[derive(Component, Default)]
+struct CameraFollow {
+    position: Vec2,
+}
+
+/// Decays over time, and the shake grows with its square so small hits stay subtle.
+#[derive(Component, Default)]
+struct CameraTrauma(f32);
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Initializing), init_camera)
+        app.add_systems(Startup, init_camera)
+            .add_observer(add_camera_trauma)
+            .add_observer(shake_on_player_damage)
             .add_systems(
                 Update,
-                camera_follow_player.run_if(in_state(GameState::Gaming)),
+                update_camera_view.run_if(resource_changed::<Config>),
+            )
+            .add_systems(
+                Update,
+                (
+                    camera_follow_player,
+                    shake_camera.after(camera_follow_player),
+                )
+                    .run_if(in_state(GameState::Gaming)),
             );
     }
 }
 
 fn init_camera(mut commands: Commands) {
-    commands.spawn(Camera2d::default());
+    commands.spawn((Camera2d, CameraFollow::default(), CameraTrauma::default()));
+}
+
+fn update_camera_view(
+    config: Res<Config>,
+    mut projection_query: Query<&mut OrthographicProjection, With<Camera>>,
+) {
+    // shows at least the configured area of the world, whatever shape the window is
+    for mut projection in projection_query.iter_mut() {
+        projection.scaling_mode = ScalingMode::AutoMin {
+            min_width: config.camera.min_view_width,
+            min_height: config.camera.min_view_height,
+        };
+    }
+}
+
+fn add_camera_trauma(
+    trigger: Trigger<ShakeCamera>,
+    settings: Res<Settings>,
+    mut camera_query: Query<&mut CameraTrauma>,
+) {
+    if !settings.screen_shake {
+        return;
+    }
+    for mut trauma in camera_query.iter_mut() {
+        trauma.0 = (trauma.0 + trigger.event().0).min(1.0);
+    }
+}
+
+fn shake_on_player_damage(
+    trigger: Trigger<Damaged>,
+    mut commands: Commands,
+    config: Res<Config>,
+    player_query: Query<(), With<Player>>,
+) {
+    if player_query.contains(trigger.entity()) {
+        commands.trigger(ShakeCamera(config.camera.player_hit_trauma));
+    }
 }
 
 fn camera_follow_player(
-    player_query: Query<&Transform, With<Player>>,
-    mut camera_query: Query<&mut Transform, (With<Camera>, Without<Player>)>,
+    time: Res<Time>,
+    config: Res<Config>,
+    aim: Res<Aim>,
+    player_query: Query<(&Transform, Ref<Player>)>,
+    mut camera_query: Query<(&mut CameraFollow, &OrthographicProjection)>,
 ) {
     if player_query.is_empty() || camera_query.is_empty() {
         return;
     }
 
-    let mut camera_transform = camera_query.single_mut();
-    let player_transform = player_query.single().translation;
-    let (x, y) = (player_transform.x, player_transform.y);
+    let (mut follow, projection) = camera_query.single_mut();
+    let (player_transform, player) = player_query.single();
+    let target =
+        player_transform.translation.truncate() + aim.direction * config.camera.lookahead_distance;
+
+    // a fresh run starts on the player instead of sweeping over from wherever the last one ended
+    if player.is_added() {
+        follow.position = target;
+    } else {
+        follow
+            .position
+            .smooth_nudge(&target, config.camera.follow_speed, time.delta_secs());
+    }
+
+    // keep the edge of the view inside the world, or centred on it if the view is larger
+    let half_view = projection.area.half_size();
+    let world = vec2(config.game.world_width, config.game.world_height);
+    let limit = (world - half_view).max(Vec2::ZERO);
+    follow.position = follow.position.clamp(-limit, limit);
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    config: Res<Config>,
+    mut camera_query: Query<(&mut Transform, &mut CameraTrauma, &CameraFollow)>,
+) {
+    if camera_query.is_empty() {
+        return;
+    }
+
+    let (mut camera_transform, mut trauma, follow) = camera_query.single_mut();
+    trauma.0 = (trauma.0 - config.camera.trauma_decay * time.delta_secs()).max(0.0);
+
+    let shake = trauma.0 * trauma.0;
+    let mut rng = rand::rng();
+    let offset = vec2(rng.random_range(-1.0..=1.0), rng.random_range(-1.0..=1.0))
+        * config.camera.max_shake_offset
+        * shake;
+    let angle = rng.random_range(-1.0..=1.0) * config.camera.max_shake_angle * shake;
 
-    camera_transform.translation = vec3(x, y, 0.0);
+    camera_transform.translation = (follow.position + offset).extend(0.0);
+    camera_transform.rotation = Quat::from_rotation_z(angle);
 }
@@ -0,0 +1,158 @@
+use crate::config::{CharacterConfig, Config};
+use crate::input::{Action, ActionInput};
+use crate::menu::{spawn_menu_button, MenuButton, MenuButtonActivated, MenuFocus, MenuSet};
+use crate::player::{CharacterStat, ChosenCharacter};
+use crate::profile::Profile;
+use crate::state::GameState;
+use bevy::prelude::*;
+
+#[derive(Component, Clone)]
+enum CharacterSelectButton {
+    Pick(String),
+    Back,
+}
+
+/// Describes whichever character has focus.
+#[derive(Component)]
+struct CharacterDetails;
+
+pub struct CharacterSelectPlugin;
+
+impl Plugin for CharacterSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::CharacterSelect), spawn_character_select)
+            .add_systems(
+                Update,
+                (
+                    handle_character_select_buttons.after(MenuSet),
+                    close_character_select,
+                    describe_focused_character.after(MenuSet),
+                )
+                    .run_if(in_state(GameState::CharacterSelect)),
+            );
+    }
+}
+
+fn spawn_character_select(mut commands: Commands, config: Res<Config>, profile: Res<Profile>) {
+    commands
+        .spawn((
+            StateScoped(GameState::CharacterSelect),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new("Choose a Character"),
+                TextFont {
+                    font_size: 36.0,
+                    ..default()
+                },
+            ));
+
+            for (row, character) in config.characters.iter().enumerate() {
+                let label = if profile.is_unlocked(character) {
+                    character.name.clone()
+                } else {
+                    format!("{} (Locked)", character.name)
+                };
+                spawn_menu_button(
+                    root,
+                    label,
+                    MenuButton { row, column: 0 },
+                    CharacterSelectButton::Pick(character.name.clone()),
+                );
+            }
+            spawn_menu_button(
+                root,
+                "Back",
+                MenuButton {
+                    row: config.characters.len(),
+                    column: 0,
+                },
+                CharacterSelectButton::Back,
+            );
+
+            root.spawn((
+                CharacterDetails,
+                Text::new(""),
+                TextLayout::new_with_justify(JustifyText::Center),
+            ));
+        });
+}
+
+fn close_character_select(actions: ActionInput, mut next_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Cancel) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn handle_character_select_buttons(
+    config: Res<Config>,
+    profile: Res<Profile>,
+    mut activated_events: EventReader<MenuButtonActivated>,
+    button_query: Query<&CharacterSelectButton>,
+    mut chosen_character: ResMut<ChosenCharacter>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in activated_events.read() {
+        match button_query.get(event.0) {
+            Ok(CharacterSelectButton::Pick(name)) => {
+                let character = config.character(Some(name));
+                if profile.is_unlocked(character) {
+                    chosen_character.0 = Some(character.name.clone());
+                    next_state.set(GameState::Initializing);
+                }
+            }
+            Ok(CharacterSelectButton::Back) => next_state.set(GameState::MainMenu),
+            Err(_) => {}
+        }
+    }
+}
+
+fn describe_focused_character(
+    config: Res<Config>,
+    profile: Res<Profile>,
+    focus: Res<MenuFocus>,
+    mut details_query: Query<&mut Text, With<CharacterDetails>>,
+) {
+    let details = config
+        .characters
+        .get(focus.row)
+        .map(|character| describe(character, &profile))
+        .unwrap_or_default();
+    for mut text in details_query.iter_mut() {
+        if text.0 != details {
+            text.0 = details.clone();
+        }
+    }
+}
+
+fn describe(character: &CharacterConfig, profile: &Profile) -> String {
+    let percent = |multiplier: f32| format!("{:.0}%", multiplier * 100.0);
+    let passive = &character.passive;
+    let passive = match passive.stat {
+        CharacterStat::Health => format!("+{:.0} HP per level", passive.per_level * 100.0),
+        stat => format!("+{} {} per level", percent(passive.per_level), stat.label()),
+    };
+    let mut details = format!(
+        "HP {:.0}  Might {}  Speed {}  Attack Speed {}  Pickup Range {}\nStarts with {}\n{passive}",
+        character.health * 100.0,
+        percent(character.damage),
+        percent(character.movement_speed),
+        percent(character.attack_speed),
+        percent(character.pickup_range),
+        character.starting_weapon.label(),
+    );
+    if let Some(condition) = character.unlock.filter(|_| !profile.is_unlocked(character)) {
+        details.push_str(&format!("\nLocked: {}", condition.label()));
+    }
+    details
+}
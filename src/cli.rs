@@ -0,0 +1,130 @@
+use crate::config::{ConfigSource, Difficulty};
+use crate::player::ChosenCharacter;
+use crate::replay::Recording;
+use crate::rng::MAX_SEED;
+use crate::settings::{DisplayMode, DisplaySettings};
+use crate::state::GameState;
+use clap::{value_parser, Parser, ValueEnum};
+
+/// Survive the horde for as long as you can.
+///
+/// Flags win over the config and settings files, and neither file is changed by them.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Balance config to load, reloaded whenever it is saved.
+    #[arg(long, value_name = "FILE", env = "CONFIG_FILE")]
+    pub config: Option<String>,
+
+    /// Check a config file and exit, the one given by --config if no FILE follows.
+    #[arg(long, value_name = "FILE", num_args = 0..=1)]
+    pub check_config: Option<Option<String>>,
+
+    /// Write the run history to FILE and exit, as JSON if it ends in .json and CSV otherwise.
+    #[arg(long, value_name = "FILE")]
+    pub export_history: Option<String>,
+
+    /// Seed for every run, so the same seed brings the same enemies and upgrades.
+    #[arg(long, conflicts_with = "replay", value_parser = value_parser!(u64).range(..=MAX_SEED))]
+    pub seed: Option<u64>,
+
+    /// Enemy strength and spawn rate, overriding `game.difficulty` from the config.
+    #[arg(long, value_enum, conflicts_with = "replay")]
+    pub difficulty: Option<Difficulty>,
+
+    /// Character to play as, by its name in the config, whether or not it is unlocked.
+    #[arg(long, value_name = "NAME", conflicts_with = "replay")]
+    pub character: Option<String>,
+
+    /// Run without a window, exiting once the run or replay is over.
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Record the first run to FILE so it can be replayed.
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<String>,
+
+    /// Play back a run recorded with --record, using its seed and difficulty.
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<String>,
+
+    /// Start windowed, borderless or fullscreen.
+    #[arg(long, value_enum, conflicts_with = "headless")]
+    pub display_mode: Option<DisplayMode>,
+
+    /// Start fullscreen, short for --display-mode fullscreen.
+    #[arg(long, conflicts_with_all = ["headless", "display_mode"])]
+    pub fullscreen: bool,
+
+    /// Start in a window of this size, for example 1280x720.
+    #[arg(long, value_name = "WxH", value_parser = parse_window_size, conflicts_with = "headless")]
+    pub window: Option<(f32, f32)>,
+
+    /// Where to go once loading finishes [default: main-menu, or game when headless or replaying]
+    #[arg(long, value_enum)]
+    pub start_state: Option<StartAt>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StartAt {
+    MainMenu,
+    Game,
+}
+
+impl Cli {
+    /// The config file and the overrides on top of it, with a replay's difficulty winning.
+    pub fn config_source(&self, replay: Option<&Recording>) -> ConfigSource {
+        ConfigSource {
+            path: self.config.clone(),
+            difficulty: replay
+                .map(|recording| recording.difficulty)
+                .or(self.difficulty),
+        }
+    }
+
+    /// The character picked with --character, or the one a replay was recorded with.
+    pub fn character(&self, replay: Option<&Recording>) -> ChosenCharacter {
+        ChosenCharacter(
+            replay
+                .map(|recording| recording.character.clone())
+                .or(self.character.clone()),
+        )
+    }
+
+    /// The saved display settings with the window flags applied on top.
+    pub fn display(&self, mut display: DisplaySettings) -> DisplaySettings {
+        if let Some((width, height)) = self.window {
+            display.width = width;
+            display.height = height;
+            display.mode = DisplayMode::Windowed;
+        }
+        if self.fullscreen {
+            display.mode = DisplayMode::Fullscreen;
+        }
+        if let Some(mode) = self.display_mode {
+            display.mode = mode;
+        }
+        display
+    }
+
+    pub fn start_state(&self) -> GameState {
+        match self.start_state {
+            Some(StartAt::MainMenu) => GameState::MainMenu,
+            Some(StartAt::Game) => GameState::Initializing,
+            // nobody is there to press play
+            None if self.headless || self.replay.is_some() => GameState::Initializing,
+            None => GameState::MainMenu,
+        }
+    }
+}
+
+fn parse_window_size(size: &str) -> Result<(f32, f32), String> {
+    let invalid = || format!("`{size}` is not a size like 1280x720");
+    let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+    let width: u32 = width.parse().map_err(|_| invalid())?;
+    let height: u32 = height.parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+    Ok((width as f32, height as f32))
+}
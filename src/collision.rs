@@ -1,10 +1,57 @@
-use crate::config::CONFIG;
-use crate::enemy::Enemy;
+use crate::config::{Config, WeaponConfig};
+use crate::enemy::{Enemy, EnemyKind};
+use crate::particles::ParticleEmitter;
 use crate::player::Player;
+use crate::rng::{GameRng, RngStream};
 use crate::state::GameState;
-use crate::weapon::Projectile;
+use crate::weapon::{Projectile, WeaponKind};
 use bevy::app::{App, Plugin};
 use bevy::prelude::*;
+use rand::Rng;
+
+/// Triggered on an entity whenever it takes damage.
+#[derive(Event, Clone, Copy)]
+pub struct Damaged {
+    pub amount: f32,
+    /// How much of `amount` came off the target's health, leaving out overkill.
+    pub dealt: f32,
+    pub critical: bool,
+    pub source: DamageSource,
+}
+
+/// What dealt a hit.
+#[derive(Clone, Copy)]
+pub enum DamageSource {
+    Weapon(WeaponKind),
+    Enemy(EnemyKind),
+}
+
+impl Damaged {
+    /// A hit from one of the player's weapons, which may land as a critical.
+    pub fn roll(
+        damage: f32,
+        weapon: WeaponKind,
+        config: &WeaponConfig,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let source = DamageSource::Weapon(weapon);
+        if rng.random_bool(config.crit_chance) {
+            Self {
+                amount: damage * config.crit_multiplier,
+                dealt: damage * config.crit_multiplier,
+                critical: true,
+                source,
+            }
+        } else {
+            Self {
+                amount: damage,
+                dealt: damage,
+                critical: false,
+                source,
+            }
+        }
+    }
+}
 
 pub struct CollisionPlugin;
 
@@ -23,21 +70,35 @@ impl Plugin for CollisionPlugin {
 
 fn handle_enemy_projectile_collision(
     mut commands: Commands,
-    projectile_query: Query<(&Transform, Entity), With<Projectile>>,
-    mut enemy_query: Query<(&Transform, &mut Enemy), With<Enemy>>,
+    config: Res<Config>,
+    projectile_query: Query<(&Transform, &Projectile, Entity), With<Projectile>>,
+    mut enemy_query: Query<(&Transform, &mut Enemy, Entity), With<Enemy>>,
+    mut rng: ResMut<GameRng>,
 ) {
     if projectile_query.is_empty() || enemy_query.is_empty() {
         return;
     }
 
-    for (projectile_transform, projectile_entity) in projectile_query.iter() {
-        for (enemy_transform, mut enemy) in enemy_query.iter_mut() {
+    for (projectile_transform, projectile, projectile_entity) in projectile_query.iter() {
+        for (enemy_transform, mut enemy, enemy_entity) in enemy_query.iter_mut() {
             if projectile_transform
                 .translation
                 .distance_squared(enemy_transform.translation)
                 <= 250.0
             {
-                enemy.health -= CONFIG.player.projectile_damage;
+                let hit = enemy.take_hit(Damaged::roll(
+                    projectile.damage,
+                    projectile.weapon,
+                    &config.weapon,
+                    rng.stream(RngStream::ProjectileCrits),
+                ));
+                commands.trigger_targets(hit, enemy_entity);
+                commands.spawn((
+                    ParticleEmitter::projectile_impact(
+                        (projectile_transform.translation - enemy_transform.translation).truncate(),
+                    ),
+                    Transform::from_translation(projectile_transform.translation),
+                ));
                 commands.entity(projectile_entity).despawn();
             }
         }
@@ -45,14 +106,16 @@ fn handle_enemy_projectile_collision(
 }
 
 fn handle_player_enemy_collision(
+    mut commands: Commands,
+    config: Res<Config>,
     mut enemy_query: Query<(&Transform, &mut Enemy), With<Enemy>>,
-    mut player_query: Query<(&Transform, &mut Player), With<Player>>,
+    mut player_query: Query<(&Transform, &mut Player, Entity), With<Player>>,
 ) {
     if player_query.is_empty() || enemy_query.is_empty() {
         return;
     }
 
-    let (player_transform, mut player) = player_query.single_mut();
+    let (player_transform, mut player, player_entity) = player_query.single_mut();
 
     for (enemy_transform, mut enemy) in enemy_query.iter_mut() {
         if player_transform
@@ -61,7 +124,15 @@ fn handle_player_enemy_collision(
             <= 250.0
             && enemy.attack_timer.elapsed_secs() > 1.0
         {
-            player.health -= 0.25;
+            let amount = 0.25 * config.game.difficulty.enemy_strength();
+            let hit = Damaged {
+                amount,
+                dealt: amount,
+                critical: false,
+                source: DamageSource::Enemy(enemy.kind),
+            };
+            player.health -= hit.amount;
+            commands.trigger_targets(hit, player_entity);
             enemy.attack_timer.reset();
         }
     }
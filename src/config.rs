@@ -1,52 +1,60 @@
-use lazy_static::lazy_static;
+use crate::player::{CharacterStat, StatUpgrade};
+use crate::weapon::WeaponKind;
+use bevy::prelude::*;
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
-use std::env;
-
-lazy_static! {
-    pub static ref CONFIG: Config = if let Ok(config_file_str) = env::var("CONFIG_FILE") {
-        if let Ok(config_str) = std::fs::read_to_string(config_file_str) {
-            toml::from_str(&config_str).unwrap()
-        } else {
-            println!("Cannot parse given config, using defaults");
-            Config::default()
-        }
-    } else {
-        println!("Config not provided or not found, using defaults");
-        Config::default()
-    };
-}
+use std::fmt;
+use std::time::SystemTime;
 
-#[derive(Serialize, Deserialize, Default)]
+/// How often the config file is checked for edits, in seconds.
+const CONFIG_POLL_INTERVAL: f32 = 0.5;
+
+/// Balance values, reloaded whenever the config file is saved.
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
-    pub app: AppConfig,
     pub game: GameConfig,
     pub sprite: SpriteConfig,
+    pub camera: CameraConfig,
     pub player: PlayerConfig,
     pub enemy: EnemyConfig,
+    pub weapon: WeaponConfig,
+    pub shop: ShopConfig,
+    pub characters: Vec<CharacterConfig>,
+    /// The window size from before it moved into the display settings, read only to warn about.
+    #[serde(skip_serializing)]
+    app: Option<toml::Table>,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct AppConfig {
-    pub window_height: f32,
-    pub window_width: f32,
-}
-
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            window_height: 1200.0,
-            window_width: 800.0,
-        }
+impl Config {
+    /// The character called `name`, or the first one if there is no such character.
+    pub fn character(&self, name: Option<&str>) -> &CharacterConfig {
+        name.and_then(|name| {
+            self.characters
+                .iter()
+                .find(|character| character.name == name)
+        })
+        .unwrap_or(&self.characters[0])
     }
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct GameConfig {
     pub world_width: f32,
     pub world_height: f32,
     pub background_color: (u8, u8, u8),
     pub decoration_density: f32,
-    pub animation_tick_interval: f32,
+    pub death_animation_duration: f32,
+    pub max_particles: usize,
+    pub difficulty: Difficulty,
+}
+
+impl GameConfig {
+    pub fn background_color(&self) -> Color {
+        let (red, green, blue) = self.background_color;
+        Color::srgb_u8(red, green, blue)
+    }
 }
 
 impl Default for GameConfig {
@@ -56,35 +64,90 @@ impl Default for GameConfig {
             world_height: 2500.0,
             background_color: (163, 116, 46),
             decoration_density: 0.00027777777,
-            animation_tick_interval: 0.1,
+            death_animation_duration: 1.5,
+            max_particles: 500,
+            difficulty: Difficulty::Normal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// Scales enemy health and the damage they deal on contact.
+    pub fn enemy_strength(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.5,
+        }
+    }
+
+    /// Scales how often enemy waves spawn.
+    pub fn spawn_rate(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.25,
         }
     }
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SpriteConfig {
-    pub spritesheet_path: String,
-    pub spritesheet_height: u32,
-    pub spritesheet_width: u32,
-    pub sprite_height: u32,
-    pub sprite_width: u32,
+    pub manifest_path: String,
     pub sprite_scale_factor: f32,
 }
 
 impl Default for SpriteConfig {
     fn default() -> Self {
         Self {
-            spritesheet_path: "sprites.png".to_string(),
-            spritesheet_height: 8,
-            spritesheet_width: 16,
-            sprite_height: 32,
-            sprite_width: 32,
+            manifest_path: "sprites.manifest.toml".to_string(),
             sprite_scale_factor: 1.5,
         }
     }
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraConfig {
+    pub min_view_width: f32,
+    pub min_view_height: f32,
+    pub follow_speed: f32,
+    pub lookahead_distance: f32,
+    pub trauma_decay: f32,
+    pub max_shake_offset: f32,
+    pub max_shake_angle: f32,
+    pub player_hit_trauma: f32,
+    pub explosion_trauma: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            min_view_width: 1000.0,
+            min_view_height: 1000.0,
+            follow_speed: 6.0,
+            lookahead_distance: 60.0,
+            trauma_decay: 1.5,
+            max_shake_offset: 12.0,
+            max_shake_angle: 0.04,
+            player_hit_trauma: 0.5,
+            explosion_trauma: 0.3,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct PlayerConfig {
     pub movement_speed: f32,
     pub attack_interval: f32,
@@ -94,6 +157,11 @@ pub struct PlayerConfig {
     pub attack_speed_multiplier_inc: f32,
     pub xp_ball_pickup_range_multiplier_inc: f32,
     pub health_per_lvlup: f32,
+    pub auto_aim_range: f32,
+    pub dash_speed_multiplier: f32,
+    pub dash_duration: f32,
+    pub dash_cooldown: f32,
+    pub revival_health: f32,
 }
 
 impl Default for PlayerConfig {
@@ -107,17 +175,25 @@ impl Default for PlayerConfig {
             attack_speed_multiplier_inc: 0.1,
             xp_ball_pickup_range_multiplier_inc: 0.1,
             health_per_lvlup: 0.25,
+            auto_aim_range: 500.0,
+            dash_speed_multiplier: 3.0,
+            dash_duration: 0.15,
+            dash_cooldown: 1.0,
+            revival_health: 0.5,
         }
     }
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct EnemyConfig {
     pub max_num_enemies: usize,
     pub enemy_spawn_interval: f32,
     pub enemy_health: f32,
     pub enemy_speed: f32,
     pub num_per_wave: usize,
+    pub gold_drop_chance: f64,
+    pub gold_per_coin: u32,
 }
 
 impl Default for EnemyConfig {
@@ -128,6 +204,922 @@ impl Default for EnemyConfig {
             enemy_health: 1.0,
             enemy_speed: 125.0,
             num_per_wave: 5,
+            gold_drop_chance: 0.25,
+            gold_per_coin: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeaponConfig {
+    pub homing_missile_unlock_level: u32,
+    pub homing_missile_interval: f32,
+    pub homing_missile_speed: f32,
+    pub homing_missile_damage: f32,
+    pub homing_missile_turn_rate: f32,
+    pub homing_missile_range: f32,
+    pub chain_lightning_unlock_level: u32,
+    pub chain_lightning_interval: f32,
+    pub chain_lightning_damage: f32,
+    pub chain_lightning_range: f32,
+    pub chain_lightning_jump_range: f32,
+    pub chain_lightning_max_jumps: usize,
+    pub chain_lightning_falloff: f32,
+    pub chain_lightning_arc_duration: f32,
+    pub max_level: u32,
+    pub damage_per_level: f32,
+    pub fire_rate_per_level: f32,
+    pub levels_per_extra_projectile: u32,
+    pub gun_spread: f32,
+    pub crit_chance: f64,
+    pub crit_multiplier: f32,
+    pub arcane_staff_projectiles: usize,
+    pub missile_swarm_salvo: usize,
+    pub thunderstorm_strikes: usize,
+    pub thunderstorm_range: f32,
+    pub evolutions: Vec<EvolutionRecipe>,
+}
+
+/// A max-level `weapon` evolves once `upgrade` has been picked `upgrade_count` times.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EvolutionRecipe {
+    pub weapon: WeaponKind,
+    pub upgrade: StatUpgrade,
+    pub upgrade_count: u32,
+    pub evolves_into: WeaponKind,
+}
+
+impl Default for WeaponConfig {
+    fn default() -> Self {
+        Self {
+            homing_missile_unlock_level: 3,
+            homing_missile_interval: 1.5,
+            homing_missile_speed: 350.0,
+            homing_missile_damage: 2.0,
+            homing_missile_turn_rate: 4.0,
+            homing_missile_range: 600.0,
+            chain_lightning_unlock_level: 6,
+            chain_lightning_interval: 2.0,
+            chain_lightning_damage: 1.5,
+            chain_lightning_range: 400.0,
+            chain_lightning_jump_range: 200.0,
+            chain_lightning_max_jumps: 4,
+            chain_lightning_falloff: 0.75,
+            chain_lightning_arc_duration: 0.15,
+            max_level: 5,
+            damage_per_level: 0.25,
+            fire_rate_per_level: 0.1,
+            levels_per_extra_projectile: 2,
+            gun_spread: 0.15,
+            crit_chance: 0.1,
+            crit_multiplier: 2.0,
+            arcane_staff_projectiles: 8,
+            missile_swarm_salvo: 3,
+            thunderstorm_strikes: 4,
+            thunderstorm_range: 500.0,
+            evolutions: vec![
+                EvolutionRecipe {
+                    weapon: WeaponKind::Gun,
+                    upgrade: StatUpgrade::AttackSpeed,
+                    upgrade_count: 3,
+                    evolves_into: WeaponKind::ArcaneStaff,
+                },
+                EvolutionRecipe {
+                    weapon: WeaponKind::HomingMissile,
+                    upgrade: StatUpgrade::PickupRange,
+                    upgrade_count: 3,
+                    evolves_into: WeaponKind::MissileSwarm,
+                },
+                EvolutionRecipe {
+                    weapon: WeaponKind::ChainLightning,
+                    upgrade: StatUpgrade::MovementSpeed,
+                    upgrade_count: 3,
+                    evolves_into: WeaponKind::Thunderstorm,
+                },
+            ],
+        }
+    }
+}
+
+/// Prices and effects of the permanent upgrades sold between runs.
+///
+/// An item's table only needs the fields it changes; the rest keep that item's defaults.
+#[derive(Serialize, Deserialize)]
+#[serde(from = "ShopOverrides")]
+pub struct ShopConfig {
+    pub starting_health: ShopItemConfig,
+    pub might: ShopItemConfig,
+    pub speed: ShopItemConfig,
+    pub magnet: ShopItemConfig,
+    pub revival: ShopItemConfig,
+}
+
+/// The first level costs `base_cost` and each one after costs `cost_growth` times the last.
+///
+/// `bonus_per_level` is a fraction of the base stat, except for revival where it counts revives.
+#[derive(Serialize)]
+pub struct ShopItemConfig {
+    pub base_cost: u32,
+    pub cost_growth: f32,
+    pub max_level: u32,
+    pub bonus_per_level: f32,
+}
+
+impl ShopItemConfig {
+    fn new(base_cost: u32, cost_growth: f32, max_level: u32, bonus_per_level: f32) -> Self {
+        Self {
+            base_cost,
+            cost_growth,
+            max_level,
+            bonus_per_level,
+        }
+    }
+}
+
+impl Default for ShopConfig {
+    fn default() -> Self {
+        Self {
+            starting_health: ShopItemConfig::new(20, 1.5, 5, 0.2),
+            might: ShopItemConfig::new(30, 1.6, 5, 0.1),
+            speed: ShopItemConfig::new(25, 1.5, 5, 0.05),
+            magnet: ShopItemConfig::new(15, 1.4, 5, 0.25),
+            revival: ShopItemConfig::new(200, 2.5, 2, 1.0),
+        }
+    }
+}
+
+/// The shop as written in the file, before the missing fields are filled in.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ShopOverrides {
+    starting_health: ShopItemOverrides,
+    might: ShopItemOverrides,
+    speed: ShopItemOverrides,
+    magnet: ShopItemOverrides,
+    revival: ShopItemOverrides,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ShopItemOverrides {
+    base_cost: Option<u32>,
+    cost_growth: Option<f32>,
+    max_level: Option<u32>,
+    bonus_per_level: Option<f32>,
+}
+
+impl ShopItemOverrides {
+    fn or(self, item: ShopItemConfig) -> ShopItemConfig {
+        ShopItemConfig {
+            base_cost: self.base_cost.unwrap_or(item.base_cost),
+            cost_growth: self.cost_growth.unwrap_or(item.cost_growth),
+            max_level: self.max_level.unwrap_or(item.max_level),
+            bonus_per_level: self.bonus_per_level.unwrap_or(item.bonus_per_level),
+        }
+    }
+}
+
+impl From<ShopOverrides> for ShopConfig {
+    fn from(overrides: ShopOverrides) -> Self {
+        let defaults = ShopConfig::default();
+        Self {
+            starting_health: overrides.starting_health.or(defaults.starting_health),
+            might: overrides.might.or(defaults.might),
+            speed: overrides.speed.or(defaults.speed),
+            magnet: overrides.magnet.or(defaults.magnet),
+            revival: overrides.revival.or(defaults.revival),
+        }
+    }
+}
+
+/// A playable character, drawn with the `<sprite>_idle`, `_run`, `_hurt` and `_die` clips.
+///
+/// `health` and the multipliers are its base stats, before permanent upgrades. Characters with
+/// an `unlock` condition can only be picked once a run has met it.
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CharacterConfig {
+    pub name: String,
+    pub sprite: String,
+    pub health: f32,
+    pub movement_speed: f32,
+    pub attack_speed: f32,
+    pub damage: f32,
+    pub pickup_range: f32,
+    pub starting_weapon: WeaponKind,
+    pub passive: PassiveBonus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unlock: Option<UnlockCondition>,
+}
+
+/// Added to `stat` every time the character levels up.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PassiveBonus {
+    pub stat: CharacterStat,
+    pub per_level: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum UnlockCondition {
+    SurviveMinutes(u32),
+    ReachLevel(u32),
+    KillEnemies(u32),
+}
+
+impl UnlockCondition {
+    pub fn label(self) -> String {
+        match self {
+            UnlockCondition::SurviveMinutes(minutes) => format!("Survive {minutes} minutes"),
+            UnlockCondition::ReachLevel(level) => format!("Reach level {level}"),
+            UnlockCondition::KillEnemies(kills) => format!("Defeat {kills} enemies"),
+        }
+    }
+}
+
+impl Default for CharacterConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            sprite: "player".to_string(),
+            health: 1.0,
+            movement_speed: 1.0,
+            attack_speed: 1.0,
+            damage: 1.0,
+            pickup_range: 1.0,
+            starting_weapon: WeaponKind::Gun,
+            passive: PassiveBonus {
+                stat: CharacterStat::Might,
+                per_level: 0.0,
+            },
+            unlock: None,
+        }
+    }
+}
+
+fn default_characters() -> Vec<CharacterConfig> {
+    vec![
+        CharacterConfig {
+            name: "Wizard".to_string(),
+            passive: PassiveBonus {
+                stat: CharacterStat::Might,
+                per_level: 0.02,
+            },
+            ..default()
+        },
+        CharacterConfig {
+            name: "Knight".to_string(),
+            sprite: "knight".to_string(),
+            health: 1.5,
+            movement_speed: 0.9,
+            attack_speed: 0.9,
+            starting_weapon: WeaponKind::ChainLightning,
+            passive: PassiveBonus {
+                stat: CharacterStat::Health,
+                per_level: 0.05,
+            },
+            unlock: Some(UnlockCondition::SurviveMinutes(10)),
+            ..default()
+        },
+        CharacterConfig {
+            name: "Rogue".to_string(),
+            sprite: "rogue".to_string(),
+            health: 0.75,
+            movement_speed: 1.2,
+            pickup_range: 1.25,
+            starting_weapon: WeaponKind::HomingMissile,
+            passive: PassiveBonus {
+                stat: CharacterStat::AttackSpeed,
+                per_level: 0.02,
+            },
+            unlock: Some(UnlockCondition::ReachLevel(20)),
+            ..default()
+        },
+    ]
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            game: GameConfig::default(),
+            sprite: SpriteConfig::default(),
+            camera: CameraConfig::default(),
+            player: PlayerConfig::default(),
+            enemy: EnemyConfig::default(),
+            weapon: WeaponConfig::default(),
+            shop: ShopConfig::default(),
+            characters: default_characters(),
+            app: None,
+        }
+    }
+}
+
+/// Where the config comes from, with values given on the command line winning over the file.
+#[derive(Resource, Clone, Default)]
+pub struct ConfigSource {
+    pub path: Option<String>,
+    pub difficulty: Option<Difficulty>,
+}
+
+impl ConfigSource {
+    /// Reads the config file, or uses the defaults without one, then applies the overrides.
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        let mut config = match &self.path {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        if let Some(difficulty) = self.difficulty {
+            config.game.difficulty = difficulty;
         }
+        Ok(config)
+    }
+}
+
+impl Config {
+    /// Reads a config file, where any section or field left out keeps its default.
+    pub fn from_file(path: &str) -> Result<Config, ConfigError> {
+        let source = std::fs::read_to_string(path).map_err(|err| ConfigError::Io {
+            path: path.to_string(),
+            err,
+        })?;
+        let config: Config = toml::from_str(&source).map_err(|err| ConfigError::Parse {
+            path: path.to_string(),
+            err,
+        })?;
+
+        let mut problems = config.validate();
+        if problems.is_empty() {
+            return Ok(config);
+        }
+        for problem in problems.iter_mut() {
+            problem.line = line_of(&source, &problem.field);
+        }
+        Err(ConfigError::Invalid {
+            path: path.to_string(),
+            problems,
+        })
+    }
+
+    /// Every value the game can't run with, named by its path in the file.
+    pub fn validate(&self) -> Vec<ConfigProblem> {
+        let mut check = Validator::default();
+
+        let game = &self.game;
+        check.positive("game.world_width", game.world_width);
+        check.positive("game.world_height", game.world_height);
+        check.non_negative("game.decoration_density", game.decoration_density);
+        check.non_negative(
+            "game.death_animation_duration",
+            game.death_animation_duration,
+        );
+
+        let sprite = &self.sprite;
+        check.check(
+            !sprite.manifest_path.is_empty(),
+            "sprite.manifest_path",
+            "must not be empty",
+        );
+        check.positive("sprite.sprite_scale_factor", sprite.sprite_scale_factor);
+
+        let camera = &self.camera;
+        check.positive("camera.min_view_width", camera.min_view_width);
+        check.positive("camera.min_view_height", camera.min_view_height);
+        check.positive("camera.follow_speed", camera.follow_speed);
+        check.non_negative("camera.lookahead_distance", camera.lookahead_distance);
+        check.positive("camera.trauma_decay", camera.trauma_decay);
+        check.non_negative("camera.max_shake_offset", camera.max_shake_offset);
+        check.non_negative("camera.max_shake_angle", camera.max_shake_angle);
+        check.fraction("camera.player_hit_trauma", camera.player_hit_trauma);
+        check.fraction("camera.explosion_trauma", camera.explosion_trauma);
+
+        let player = &self.player;
+        check.positive("player.movement_speed", player.movement_speed);
+        check.positive("player.attack_interval", player.attack_interval);
+        check.positive("player.projectile_speed", player.projectile_speed);
+        check.non_negative("player.projectile_damage", player.projectile_damage);
+        check.non_negative(
+            "player.movement_speed_multiplier_inc",
+            player.movement_speed_multiplier_inc,
+        );
+        check.non_negative(
+            "player.attack_speed_multiplier_inc",
+            player.attack_speed_multiplier_inc,
+        );
+        check.non_negative(
+            "player.xp_ball_pickup_range_multiplier_inc",
+            player.xp_ball_pickup_range_multiplier_inc,
+        );
+        check.non_negative("player.health_per_lvlup", player.health_per_lvlup);
+        check.positive("player.auto_aim_range", player.auto_aim_range);
+        check.check(
+            player.dash_speed_multiplier >= 1.0,
+            "player.dash_speed_multiplier",
+            "must be at least 1",
+        );
+        check.non_negative("player.dash_duration", player.dash_duration);
+        check.non_negative("player.dash_cooldown", player.dash_cooldown);
+        check.check(
+            player.revival_health > 0.0 && player.revival_health <= 1.0,
+            "player.revival_health",
+            "must be greater than 0 and at most 1",
+        );
+
+        let enemy = &self.enemy;
+        check.check(
+            enemy.num_per_wave > 0,
+            "enemy.num_per_wave",
+            "must be at least 1",
+        );
+        check.check(
+            enemy.max_num_enemies >= enemy.num_per_wave,
+            "enemy.max_num_enemies",
+            format!(
+                "must be at least enemy.num_per_wave ({})",
+                enemy.num_per_wave
+            ),
+        );
+        check.positive("enemy.enemy_spawn_interval", enemy.enemy_spawn_interval);
+        check.positive("enemy.enemy_health", enemy.enemy_health);
+        check.positive("enemy.enemy_speed", enemy.enemy_speed);
+        check.check(
+            (0.0..=1.0).contains(&enemy.gold_drop_chance),
+            "enemy.gold_drop_chance",
+            "must be between 0 and 1",
+        );
+
+        let weapon = &self.weapon;
+        check.positive(
+            "weapon.homing_missile_interval",
+            weapon.homing_missile_interval,
+        );
+        check.positive("weapon.homing_missile_speed", weapon.homing_missile_speed);
+        check.non_negative("weapon.homing_missile_damage", weapon.homing_missile_damage);
+        check.non_negative(
+            "weapon.homing_missile_turn_rate",
+            weapon.homing_missile_turn_rate,
+        );
+        check.positive("weapon.homing_missile_range", weapon.homing_missile_range);
+        check.positive(
+            "weapon.chain_lightning_interval",
+            weapon.chain_lightning_interval,
+        );
+        check.non_negative(
+            "weapon.chain_lightning_damage",
+            weapon.chain_lightning_damage,
+        );
+        check.positive("weapon.chain_lightning_range", weapon.chain_lightning_range);
+        check.positive(
+            "weapon.chain_lightning_jump_range",
+            weapon.chain_lightning_jump_range,
+        );
+        check.fraction(
+            "weapon.chain_lightning_falloff",
+            weapon.chain_lightning_falloff,
+        );
+        check.positive(
+            "weapon.chain_lightning_arc_duration",
+            weapon.chain_lightning_arc_duration,
+        );
+        check.check(
+            weapon.max_level >= 1,
+            "weapon.max_level",
+            "must be at least 1",
+        );
+        check.non_negative("weapon.damage_per_level", weapon.damage_per_level);
+        check.non_negative("weapon.fire_rate_per_level", weapon.fire_rate_per_level);
+        check.check(
+            weapon.levels_per_extra_projectile >= 1,
+            "weapon.levels_per_extra_projectile",
+            "must be at least 1",
+        );
+        check.non_negative("weapon.gun_spread", weapon.gun_spread);
+        check.check(
+            (0.0..=1.0).contains(&weapon.crit_chance),
+            "weapon.crit_chance",
+            "must be between 0 and 1",
+        );
+        check.check(
+            weapon.crit_multiplier >= 1.0,
+            "weapon.crit_multiplier",
+            "must be at least 1",
+        );
+        check.check(
+            weapon.arcane_staff_projectiles >= 1,
+            "weapon.arcane_staff_projectiles",
+            "must be at least 1",
+        );
+        check.check(
+            weapon.missile_swarm_salvo >= 1,
+            "weapon.missile_swarm_salvo",
+            "must be at least 1",
+        );
+        check.check(
+            weapon.thunderstorm_strikes >= 1,
+            "weapon.thunderstorm_strikes",
+            "must be at least 1",
+        );
+        check.positive("weapon.thunderstorm_range", weapon.thunderstorm_range);
+        for (index, recipe) in weapon.evolutions.iter().enumerate() {
+            check.check(
+                recipe.weapon != recipe.evolves_into,
+                format!("weapon.evolutions[{index}].evolves_into"),
+                "must be a different weapon",
+            );
+        }
+
+        for (name, item) in [
+            ("starting_health", &self.shop.starting_health),
+            ("might", &self.shop.might),
+            ("speed", &self.shop.speed),
+            ("magnet", &self.shop.magnet),
+            ("revival", &self.shop.revival),
+        ] {
+            check.check(
+                item.cost_growth >= 1.0,
+                format!("shop.{name}.cost_growth"),
+                "must be at least 1",
+            );
+            check.non_negative(
+                &format!("shop.{name}.bonus_per_level"),
+                item.bonus_per_level,
+            );
+        }
+
+        check.check(
+            !self.characters.is_empty(),
+            "characters",
+            "must list at least one character",
+        );
+        for (index, character) in self.characters.iter().enumerate() {
+            let field = |name: &str| format!("characters[{index}].{name}");
+            check.check(
+                !character.name.is_empty(),
+                field("name"),
+                "must not be empty",
+            );
+            check.check(
+                !self.characters[..index]
+                    .iter()
+                    .any(|other| other.name == character.name),
+                field("name"),
+                format!("`{}` is used by another character", character.name),
+            );
+            check.check(
+                !character.sprite.is_empty(),
+                field("sprite"),
+                "must not be empty",
+            );
+            check.positive(&field("health"), character.health);
+            check.positive(&field("movement_speed"), character.movement_speed);
+            check.positive(&field("attack_speed"), character.attack_speed);
+            check.positive(&field("damage"), character.damage);
+            check.positive(&field("pickup_range"), character.pickup_range);
+            check.check(
+                character.passive.per_level >= 0.0,
+                field("passive"),
+                "must not have a negative per_level",
+            );
+            // someone has to be playable before anything is unlocked
+            check.check(
+                index > 0 || character.unlock.is_none(),
+                field("unlock"),
+                "must not be set on the first character",
+            );
+        }
+
+        check.problems
+    }
+
+    /// Every field that differs in `other`, as `path: old -> new`.
+    fn changes_to(&self, other: &Config) -> Vec<String> {
+        let mut changes = Vec::new();
+        if let (Ok(old), Ok(new)) = (toml::Value::try_from(self), toml::Value::try_from(other)) {
+            diff_values("", &old, &new, &mut changes);
+        }
+        changes
+    }
+}
+
+fn diff_values(path: &str, old: &toml::Value, new: &toml::Value, changes: &mut Vec<String>) {
+    match (old, new) {
+        (toml::Value::Table(old), toml::Value::Table(new)) => {
+            for (key, old_value) in old.iter() {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                if let Some(new_value) = new.get(key) {
+                    diff_values(&path, old_value, new_value, changes);
+                }
+            }
+        }
+        _ if old != new => changes.push(format!("{path}: {old} -> {new}")),
+        _ => {}
+    }
+}
+
+/// A value that failed validation, with `field` as its dotted path in the file.
+pub struct ConfigProblem {
+    pub field: String,
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {} {}", self.field, self.message),
+            None => write!(f, "{} {}", self.field, self.message),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Validator {
+    problems: Vec<ConfigProblem>,
+}
+
+impl Validator {
+    fn check(&mut self, ok: bool, field: impl Into<String>, message: impl Into<String>) {
+        if !ok {
+            self.problems.push(ConfigProblem {
+                field: field.into(),
+                message: message.into(),
+                line: None,
+            });
+        }
+    }
+
+    fn positive(&mut self, field: &str, value: f32) {
+        self.check(value > 0.0, field, "must be greater than 0");
+    }
+
+    fn non_negative(&mut self, field: &str, value: f32) {
+        self.check(value >= 0.0, field, "must not be negative");
+    }
+
+    fn fraction(&mut self, field: &str, value: f32) {
+        self.check(
+            (0.0..=1.0).contains(&value),
+            field,
+            "must be between 0 and 1",
+        );
+    }
+}
+
+pub enum ConfigError {
+    Io {
+        path: String,
+        err: std::io::Error,
+    },
+    Parse {
+        path: String,
+        err: toml::de::Error,
+    },
+    Invalid {
+        path: String,
+        problems: Vec<ConfigProblem>,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, err } => write!(f, "cannot read config {path}: {err}"),
+            ConfigError::Parse { path, err } => write!(f, "cannot parse config {path}: {err}"),
+            ConfigError::Invalid { path, problems } => {
+                write!(f, "invalid config {path}:")?;
+                for problem in problems {
+                    write!(f, "\n  {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Finds the line a field is set on, if it is set at all, for fields like `enemy.num_per_wave`
+/// or `weapon.evolutions[1].upgrade_count`.
+fn line_of(source: &str, field: &str) -> Option<usize> {
+    let (table, key) = field.rsplit_once('.')?;
+    let (table, index) = match table
+        .strip_suffix(']')
+        .and_then(|table| table.split_once('['))
+    {
+        Some((table, index)) => (table, index.parse().ok()?),
+        None => (table, 0),
+    };
+
+    let mut in_table = false;
+    let mut occurrences = 0;
+    for (number, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            let header = line.trim_matches(|c| c == '[' || c == ']').trim();
+            in_table = header == table && {
+                occurrences += 1;
+                occurrences - 1 == index
+            };
+        } else if in_table && line.split('=').next().map(str::trim) == Some(key) {
+            return Some(number + 1);
+        }
+    }
+    None
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// The config file and when it was last saved, polled so edits apply without a restart.
+#[derive(Resource)]
+struct ConfigWatcher {
+    path: String,
+    modified: Option<SystemTime>,
+    poll_timer: Timer,
+}
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConfigSource>()
+            .init_resource::<Config>()
+            .add_systems(Startup, (watch_config_file, warn_deprecated_sections))
+            .add_systems(
+                Update,
+                reload_config.run_if(resource_exists::<ConfigWatcher>),
+            );
+    }
+}
+
+fn watch_config_file(mut commands: Commands, source: Res<ConfigSource>) {
+    let Some(path) = source.path.clone() else {
+        return;
+    };
+    commands.insert_resource(ConfigWatcher {
+        modified: modified_time(&path),
+        path,
+        poll_timer: Timer::from_seconds(CONFIG_POLL_INTERVAL, TimerMode::Repeating),
+    });
+}
+
+fn warn_deprecated_sections(config: Res<Config>) {
+    if config.app.is_some() {
+        warn!(
+            "The [app] config section is no longer used, set the window in the [display] settings instead"
+        );
+    }
+}
+
+fn reload_config(
+    time: Res<Time<Real>>,
+    source: Res<ConfigSource>,
+    mut watcher: ResMut<ConfigWatcher>,
+    mut config: ResMut<Config>,
+) {
+    if !watcher.poll_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let modified = modified_time(&watcher.path);
+    if modified == watcher.modified {
+        return;
+    }
+    watcher.modified = modified;
+
+    // a broken save keeps the game running on the last good values until it is fixed
+    match source.load() {
+        Ok(new_config) => {
+            let changes = config.changes_to(&new_config);
+            if changes.is_empty() {
+                return;
+            }
+            for change in changes.iter() {
+                info!("Config reloaded, {change}");
+            }
+            *config = new_config;
+        }
+        Err(err) => warn!("{err}\nKeeping the previous config"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each problem in `source` as its field and the line it is reported on.
+    fn problems(source: &str) -> Vec<(String, Option<usize>)> {
+        let config: Config = toml::from_str(source).unwrap();
+        config
+            .validate()
+            .into_iter()
+            .map(|problem| {
+                let line = line_of(source, &problem.field);
+                (problem.field, line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(Config::default().validate().is_empty());
+    }
+
+    #[test]
+    fn missing_sections_keep_their_defaults() {
+        assert!(problems("").is_empty());
+
+        let config: Config = toml::from_str("[enemy]\nnum_per_wave = 2\n").unwrap();
+        assert_eq!(config.enemy.num_per_wave, 2);
+        assert_eq!(
+            config.enemy.max_num_enemies,
+            EnemyConfig::default().max_num_enemies
+        );
+        assert_eq!(config.game.world_width, GameConfig::default().world_width);
+    }
+
+    #[test]
+    fn out_of_range_values_are_reported_on_their_line() {
+        let source = "\
+[game]
+world_width = -1.0
+
+[enemy]
+gold_drop_chance = 2.0
+";
+        assert_eq!(
+            problems(source),
+            [
+                ("game.world_width".to_string(), Some(2)),
+                ("enemy.gold_drop_chance".to_string(), Some(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn fields_checked_against_each_other_are_reported() {
+        let source = "[enemy]\nnum_per_wave = 10\nmax_num_enemies = 5\n";
+        assert_eq!(
+            problems(source),
+            [("enemy.max_num_enemies".to_string(), Some(3))]
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(toml::from_str::<Config>("[enemy]\nnum_per_waves = 2\n").is_err());
+        assert!(toml::from_str::<Config>("[enemies]\nnum_per_wave = 2\n").is_err());
+    }
+
+    #[test]
+    fn old_app_section_is_accepted() {
+        let config: Config =
+            toml::from_str("[app]\nwindow_height = 720.0\nwindow_width = 1280.0\n").unwrap();
+        assert!(config.app.is_some());
+        assert!(!toml::to_string(&config).unwrap().contains("[app]"));
+    }
+
+    #[test]
+    fn evolution_problems_are_reported_on_their_own_table() {
+        let source = "\
+[weapon]
+max_level = 5
+
+[[weapon.evolutions]]
+weapon = \"gun\"
+upgrade = \"attack_speed\"
+upgrade_count = 3
+evolves_into = \"arcane_staff\"
+
+[[weapon.evolutions]]
+weapon = \"gun\"
+upgrade = \"pickup_range\"
+upgrade_count = 3
+evolves_into = \"gun\"
+";
+        assert_eq!(
+            problems(source),
+            [("weapon.evolutions[1].evolves_into".to_string(), Some(14))]
+        );
+    }
+
+    #[test]
+    fn line_of_skips_keys_in_other_tables() {
+        let source = "\
+[player]
+health = 3.0
+
+[enemy]
+health = 1.0
+";
+        assert_eq!(line_of(source, "enemy.health"), Some(5));
+        assert_eq!(line_of(source, "enemy.speed"), None);
+        assert_eq!(line_of(source, "weapon.evolutions[0].weapon"), None);
     }
 }
@@ -1,106 +1,192 @@
-use crate::animation::AnimationTimer;
-use crate::config::CONFIG;
+use crate::animation::{AnimationController, AnimationState, SpriteAnimation};
+use crate::audio::{PlaySound, SoundEffect};
+use crate::collision::Damaged;
+use crate::config::Config;
+use crate::game_over::RunStats;
+use crate::gold::GoldCoin;
+use crate::particles::ParticleEmitter;
 use crate::player::Player;
+use crate::replay::replaying;
 use crate::resources::GlobalTextureAtlas;
-use crate::state::GameState;
-use crate::util::get_sprite_index;
+use crate::rng::{GameRng, RngStream};
+use crate::state::{run_scoped, GameState, InRun};
 use crate::xp_ball::XPBall;
 use bevy::app::{App, Plugin};
-use bevy::math::vec3;
+use bevy::math::{vec2, vec3};
 use bevy::prelude::*;
-use bevy::time::common_conditions::on_timer;
 use bevy::time::Stopwatch;
 use rand::Rng;
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Spots tried for each enemy, all of them drawn every time so the spawn stream stays the same
+/// length whatever the view covers.
+const SPAWN_CANDIDATES: usize = 32;
+
+/// What sort of enemy something is, so a run can count its kills of each.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnemyKind {
+    Goblin,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 1] = [EnemyKind::Goblin];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EnemyKind::Goblin => "Goblin",
+        }
+    }
+}
 
 #[derive(Component)]
-#[require(Sprite)]
+#[require(Sprite, StateScoped<InRun>(run_scoped))]
 pub struct Enemy {
+    pub kind: EnemyKind,
     pub health: f32,
     pub attack_timer: Stopwatch,
 }
 
-impl Default for Enemy {
-    fn default() -> Self {
+impl Enemy {
+    pub fn new(kind: EnemyKind, health: f32) -> Self {
         Self {
-            health: CONFIG.enemy.enemy_health,
+            kind,
+            health,
             attack_timer: Stopwatch::new(),
         }
     }
+
+    /// Takes a hit off the enemy's health, keeping only what it had left as damage dealt so
+    /// overkill and hits on an enemy already dead this frame do not count.
+    pub fn take_hit(&mut self, mut hit: Damaged) -> Damaged {
+        hit.dealt = hit.amount.min(self.health.max(0.0));
+        self.health -= hit.amount;
+        hit
+    }
 }
 
+/// An enemy that has been killed and is playing its death animation.
+#[derive(Component)]
+pub struct EnemyCorpse;
+
+/// The part of the world on screen, which enemies spawn outside of.
+///
+/// Follows the camera, except during a replay where it comes from the recording so enemies
+/// spawn in the same places whatever the window size, or with no window at all.
+#[derive(Resource, Default, Clone, Copy, Deref)]
+pub struct SpawnView(pub Rect);
+
+/// Time since the last wave of enemies spawned.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct EnemySpawnTimer(pub Stopwatch);
+
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            spawn_enemies
-                .run_if(in_state(GameState::Gaming))
-                .run_if(on_timer(Duration::from_secs_f32(
-                    CONFIG.enemy.enemy_spawn_interval,
-                ))),
-        )
-        .add_systems(
-            Update,
-            (
-                update_enemy_transform,
-                despawn_dead_enemy,
-                update_enemy_attack_timer,
+        app.init_resource::<EnemySpawnTimer>()
+            .init_resource::<SpawnView>()
+            .add_systems(OnEnter(GameState::Initializing), reset_spawn_timer)
+            .add_systems(
+                Update,
+                (
+                    tick_spawn_timer,
+                    track_spawn_view.run_if(not(replaying)),
+                    (spawn_enemies, reset_spawn_timer)
+                        .chain()
+                        .run_if(spawn_interval_elapsed),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Gaming)),
             )
-                .run_if(in_state(GameState::Gaming)),
+            .add_systems(
+                Update,
+                (
+                    update_enemy_transform,
+                    despawn_dead_enemy,
+                    despawn_enemy_corpses,
+                    update_enemy_attack_timer,
+                )
+                    .run_if(in_state(GameState::Gaming)),
+            );
+    }
+}
+
+fn tick_spawn_timer(time: Res<Time>, mut spawn_timer: ResMut<EnemySpawnTimer>) {
+    spawn_timer.tick(time.delta());
+}
+
+fn reset_spawn_timer(mut spawn_timer: ResMut<EnemySpawnTimer>) {
+    spawn_timer.reset();
+}
+
+/// Like `on_timer`, but reads the interval every frame so a reloaded config applies straight away.
+fn spawn_interval_elapsed(config: Res<Config>, spawn_timer: Res<EnemySpawnTimer>) -> bool {
+    spawn_timer.elapsed_secs()
+        >= config.enemy.enemy_spawn_interval / config.game.difficulty.spawn_rate()
+}
+
+fn track_spawn_view(
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    mut spawn_view: ResMut<SpawnView>,
+) {
+    for (camera_transform, projection) in camera_query.iter() {
+        spawn_view.0 = Rect::from_center_half_size(
+            camera_transform.translation.truncate(),
+            projection.area.half_size(),
         );
     }
 }
 
 fn spawn_enemies(
     mut commands: Commands,
+    config: Res<Config>,
     texture_handle: Res<GlobalTextureAtlas>,
-    player_query: Query<&Transform, With<Player>>,
-    enemy_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    player_query: Query<(), With<Player>>,
+    enemy_query: Query<(), With<Enemy>>,
+    spawn_view: Res<SpawnView>,
+    mut rng: ResMut<GameRng>,
 ) {
     let num_enemies = enemy_query.iter().len();
-    let enemies_spawn_count =
-        (CONFIG.enemy.max_num_enemies - num_enemies).min(CONFIG.enemy.num_per_wave);
-
-    if num_enemies >= CONFIG.enemy.max_num_enemies || player_query.is_empty() {
+    if num_enemies >= config.enemy.max_num_enemies || player_query.is_empty() {
         return;
     }
+    let enemies_spawn_count =
+        (config.enemy.max_num_enemies - num_enemies).min(config.enemy.num_per_wave);
 
-    let player_transform = player_query.single();
-
-    let mut rng = rand::rng();
+    let rng = rng.stream(RngStream::EnemySpawns);
     for _ in 0..enemies_spawn_count {
-        let mut x = rng.random_range(-CONFIG.game.world_width..CONFIG.game.world_width);
-        while (x - player_transform.translation.x).abs() <= CONFIG.app.window_width / 2.0 {
-            x = rng.random_range(-CONFIG.game.world_width..CONFIG.game.world_width);
-        }
-        let mut y = rng.random_range(-CONFIG.game.world_height..CONFIG.game.world_height);
-        while (y - player_transform.translation.y).abs() <= CONFIG.app.window_height / 2.0 {
-            y = rng.random_range(-CONFIG.game.world_width..CONFIG.game.world_width);
-        }
+        let candidates: Vec<Vec2> = (0..SPAWN_CANDIDATES)
+            .map(|_| {
+                vec2(
+                    rng.random_range(-config.game.world_width..config.game.world_width),
+                    rng.random_range(-config.game.world_height..config.game.world_height),
+                )
+            })
+            .collect();
+        // spawn out of view so enemies walk in rather than popping up around the player, and
+        // skip the enemy if the view covers every spot tried
+        let Some(Vec2 { x, y }) = candidates
+            .into_iter()
+            .find(|position| !spawn_view.contains(*position))
+        else {
+            continue;
+        };
         commands.spawn((
-            Enemy::default(),
-            Sprite {
-                image: texture_handle.image.clone().unwrap(),
-                texture_atlas: Some(TextureAtlas {
-                    layout: texture_handle.layout.clone().unwrap(),
-                    index: get_sprite_index(3, 0),
-                }),
-                ..default()
-            },
+            Enemy::new(
+                EnemyKind::Goblin,
+                config.enemy.enemy_health * config.game.difficulty.enemy_strength(),
+            ),
+            texture_handle.animated_sprite("enemy"),
             Transform::from_translation(vec3(x, y, 1.0))
-                .with_scale(Vec3::splat(CONFIG.sprite.sprite_scale_factor)),
-            AnimationTimer(Timer::from_seconds(
-                CONFIG.game.animation_tick_interval,
-                TimerMode::Repeating,
-            )),
+                .with_scale(Vec3::splat(config.sprite.sprite_scale_factor)),
         ));
     }
 }
 
 fn update_enemy_transform(
     time: Res<Time>,
+    config: Res<Config>,
     mut enemy_query: Query<&mut Transform, (With<Enemy>, Without<Player>)>,
     player_query: Query<&Transform, With<Player>>,
 ) {
@@ -111,27 +197,106 @@ fn update_enemy_transform(
     let player_position = player_query.single().translation;
     for mut transform in enemy_query.iter_mut() {
         let direction = (player_position - transform.translation).normalize();
-        transform.translation += direction * CONFIG.enemy.enemy_speed * time.delta_secs()
+        transform.translation += direction * config.enemy.enemy_speed * time.delta_secs()
     }
 }
 
 fn despawn_dead_enemy(
     mut commands: Commands,
-    enemy_query: Query<(&Transform, &Enemy, Entity), With<Enemy>>,
+    mut enemy_query: Query<
+        (
+            &Transform,
+            &Enemy,
+            &mut AnimationController,
+            &mut SpriteAnimation,
+            Entity,
+        ),
+        With<Enemy>,
+    >,
     texture_handle: Res<GlobalTextureAtlas>,
+    config: Res<Config>,
+    mut run_stats: ResMut<RunStats>,
+    mut rng: ResMut<GameRng>,
 ) {
     if enemy_query.is_empty() {
         return;
     }
 
-    for (enemy_transform, enemy, entity) in enemy_query.iter() {
+    for (enemy_transform, enemy, mut controller, mut animation, entity) in enemy_query.iter_mut() {
         if enemy.health <= 0.0 {
-            XPBall::spawn(&mut commands, enemy_transform.translation, &texture_handle);
+            XPBall::spawn(
+                &mut commands,
+                enemy_transform.translation,
+                &texture_handle,
+                &config,
+            );
+            if rng
+                .stream(RngStream::Loot)
+                .random_bool(config.enemy.gold_drop_chance)
+            {
+                // beside the XP ball rather than hidden under it
+                GoldCoin::spawn(
+                    &mut commands,
+                    enemy_transform.translation + vec3(16.0, 0.0, 0.0),
+                    &texture_handle,
+                    &config,
+                );
+            }
+            commands.trigger(PlaySound(SoundEffect::EnemyDeath));
+            commands.spawn((
+                ParticleEmitter::enemy_death(),
+                Transform::from_translation(enemy_transform.translation),
+            ));
+            // no longer an enemy, so nothing targets or collides with it while it falls
+            controller.play(AnimationState::Die, &mut animation);
+            commands
+                .entity(entity)
+                .remove::<Enemy>()
+                .insert(EnemyCorpse);
+            run_stats.kills += 1;
+            *run_stats.kills_by_enemy.entry(enemy.kind).or_default() += 1;
+        }
+    }
+}
+
+fn despawn_enemy_corpses(
+    mut commands: Commands,
+    corpse_query: Query<(&SpriteAnimation, Entity), With<EnemyCorpse>>,
+) {
+    for (animation, entity) in corpse_query.iter() {
+        if animation.finished() {
             commands.entity(entity).despawn();
         }
     }
 }
 
+/// Returns up to `count` enemies within `radius` of `origin`, nearest first.
+pub fn nearest_enemies(
+    enemies: impl IntoIterator<Item = (Entity, Vec2)>,
+    origin: Vec2,
+    radius: f32,
+    count: usize,
+) -> Vec<(Entity, Vec2)> {
+    let mut in_range: Vec<(Entity, Vec2)> = enemies
+        .into_iter()
+        .filter(|(_, position)| position.distance_squared(origin) <= radius * radius)
+        .collect();
+    in_range.sort_by(|(_, a), (_, b)| {
+        a.distance_squared(origin)
+            .total_cmp(&b.distance_squared(origin))
+    });
+    in_range.truncate(count);
+    in_range
+}
+
+pub fn nearest_enemy(
+    enemies: impl IntoIterator<Item = (Entity, Vec2)>,
+    origin: Vec2,
+    radius: f32,
+) -> Option<(Entity, Vec2)> {
+    nearest_enemies(enemies, origin, radius, 1).pop()
+}
+
 fn update_enemy_attack_timer(time: Res<Time>, mut enemy_query: Query<&mut Enemy, With<Enemy>>) {
     for mut enemy in enemy_query.iter_mut() {
         enemy.attack_timer.tick(time.delta());
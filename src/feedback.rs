@@ -0,0 +1,115 @@
+use crate::collision::Damaged;
+use crate::enemy::Enemy;
+use crate::settings::Settings;
+use crate::state::{run_scoped, GameState, InRun};
+use bevy::prelude::*;
+
+const DAMAGE_NUMBER_DURATION: f32 = 0.7;
+const DAMAGE_NUMBER_RISE_SPEED: f32 = 60.0;
+const HIT_FLASH_DURATION: f32 = 0.1;
+/// Sprite colours multiply the texture, so an overbright tint washes it out towards white.
+const HIT_FLASH_COLOR: Color = Color::linear_rgb(8.0, 8.0, 8.0);
+
+#[derive(Component)]
+#[require(StateScoped<InRun>(run_scoped))]
+struct DamageNumber(Timer);
+
+#[derive(Component)]
+struct HitFlash(Timer);
+
+pub struct FeedbackPlugin;
+
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(spawn_damage_number)
+            .add_observer(start_hit_flash)
+            .add_systems(
+                Update,
+                (update_damage_numbers, update_hit_flashes).run_if(in_state(GameState::Gaming)),
+            );
+    }
+}
+
+fn spawn_damage_number(
+    trigger: Trigger<Damaged>,
+    mut commands: Commands,
+    settings: Res<Settings>,
+    enemy_query: Query<&Transform, With<Enemy>>,
+) {
+    if !settings.damage_numbers {
+        return;
+    }
+    let Ok(enemy_transform) = enemy_query.get(trigger.entity()) else {
+        return;
+    };
+
+    let hit = trigger.event();
+    let amount = (hit.amount * 10.0).round() / 10.0;
+    let (text, font_size, color) = if hit.critical {
+        (format!("{amount}!"), 28.0, Color::srgb(1.0, 0.8, 0.1))
+    } else {
+        (amount.to_string(), 18.0, Color::WHITE)
+    };
+
+    commands.spawn((
+        DamageNumber(Timer::from_seconds(DAMAGE_NUMBER_DURATION, TimerMode::Once)),
+        Text2d::new(text),
+        TextFont {
+            font_size,
+            ..default()
+        },
+        TextColor(color),
+        Transform::from_translation(enemy_transform.translation.truncate().extend(20.0)),
+    ));
+}
+
+fn update_damage_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut number_query: Query<(&mut DamageNumber, &mut Transform, &mut TextColor, Entity)>,
+) {
+    for (mut damage_number, mut transform, mut text_color, entity) in number_query.iter_mut() {
+        damage_number.0.tick(time.delta());
+        if damage_number.0.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        transform.translation.y += DAMAGE_NUMBER_RISE_SPEED * time.delta_secs();
+        text_color.0.set_alpha(damage_number.0.fraction_remaining());
+    }
+}
+
+fn start_hit_flash(
+    trigger: Trigger<Damaged>,
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut sprite_query: Query<&mut Sprite>,
+) {
+    if !settings.hit_flash {
+        return;
+    }
+    if let Ok(mut sprite) = sprite_query.get_mut(trigger.entity()) {
+        sprite.color = HIT_FLASH_COLOR;
+        commands
+            .entity(trigger.entity())
+            .insert(HitFlash(Timer::from_seconds(
+                HIT_FLASH_DURATION,
+                TimerMode::Once,
+            )));
+    }
+}
+
+fn update_hit_flashes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flash_query: Query<(&mut HitFlash, &mut Sprite, Entity)>,
+) {
+    for (mut hit_flash, mut sprite, entity) in flash_query.iter_mut() {
+        hit_flash.0.tick(time.delta());
+        if hit_flash.0.finished() {
+            sprite.color = Color::WHITE;
+            commands.entity(entity).remove::<HitFlash>();
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::animation::{AnimationController, AnimationState, SpriteAnimation};
+use crate::collision::{DamageSource, Damaged};
+use crate::config::Config;
+use crate::enemy::EnemyKind;
+use crate::menu::{spawn_menu_button, MenuButton, MenuButtonActivated, MenuSet};
+use crate::player::Player;
+use crate::state::GameState;
+use crate::weapon::WeaponKind;
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+use std::collections::HashMap;
+
+/// What the current run has achieved so far, shown on the game-over screen and kept in the
+/// run history.
+#[derive(Resource, Default)]
+pub struct RunStats {
+    pub time_survived: Stopwatch,
+    pub kills: u32,
+    pub gold: u32,
+    pub kills_by_enemy: HashMap<EnemyKind, u32>,
+    pub damage_by_weapon: HashMap<WeaponKind, f32>,
+    pub xp_collected: u32,
+    /// Whatever hurt the player last, which is what killed them once the run is over.
+    pub last_hit_by: Option<EnemyKind>,
+}
+
+impl RunStats {
+    /// Time survived as `m:ss`.
+    pub fn time_survived_label(&self) -> String {
+        let seconds = self.time_survived.elapsed_secs() as u32;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[derive(Resource, Deref, DerefMut)]
+struct DeathTimer(Timer);
+
+#[derive(Component, Clone, Copy)]
+enum GameOverButton {
+    Restart,
+    MainMenu,
+}
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .add_observer(count_damage)
+            .add_systems(OnEnter(GameState::Initializing), reset_run_stats)
+            .add_systems(OnEnter(GameState::Dying), start_death_animation)
+            .add_systems(OnExit(GameState::Dying), finish_death_animation)
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over_screen)
+            .add_systems(Update, update_run_stats.run_if(in_state(GameState::Gaming)))
+            .add_systems(
+                Update,
+                wait_for_death_animation.run_if(in_state(GameState::Dying)),
+            )
+            .add_systems(
+                Update,
+                handle_game_over_buttons
+                    .after(MenuSet)
+                    .run_if(in_state(GameState::GameOver)),
+            );
+    }
+}
+
+fn reset_run_stats(mut run_stats: ResMut<RunStats>) {
+    *run_stats = RunStats::default();
+}
+
+fn count_damage(trigger: Trigger<Damaged>, mut run_stats: ResMut<RunStats>) {
+    match trigger.source {
+        DamageSource::Weapon(weapon) => {
+            *run_stats.damage_by_weapon.entry(weapon).or_default() += trigger.dealt;
+        }
+        DamageSource::Enemy(enemy) => run_stats.last_hit_by = Some(enemy),
+    }
+}
+
+fn update_run_stats(time: Res<Time>, mut run_stats: ResMut<RunStats>) {
+    run_stats.time_survived.tick(time.delta());
+}
+
+fn start_death_animation(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut player_query: Query<(&mut AnimationController, &mut SpriteAnimation), With<Player>>,
+) {
+    for (mut controller, mut animation) in player_query.iter_mut() {
+        controller.play(AnimationState::Die, &mut animation);
+    }
+    commands.insert_resource(DeathTimer(Timer::from_seconds(
+        config.game.death_animation_duration,
+        TimerMode::Once,
+    )));
+}
+
+fn finish_death_animation(mut commands: Commands) {
+    commands.remove_resource::<DeathTimer>();
+}
+
+fn wait_for_death_animation(
+    time: Res<Time>,
+    mut death_timer: ResMut<DeathTimer>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // the death clip itself is played by the animation controller
+    death_timer.tick(time.delta());
+    if death_timer.finished() {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    run_stats: Res<RunStats>,
+    player_query: Query<&Player>,
+) {
+    let level = player_query
+        .iter()
+        .map(|player| player.level)
+        .max()
+        .unwrap_or(0);
+
+    commands
+        .spawn((
+            StateScoped(GameState::GameOver),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new("Game Over"),
+                TextFont {
+                    font_size: 48.0,
+                    ..default()
+                },
+            ));
+            root.spawn(Text::new(format!(
+                "Survived {}",
+                run_stats.time_survived_label()
+            )));
+            root.spawn(Text::new(format!("Level {level}")));
+            root.spawn(Text::new(format!("{} kills", run_stats.kills)));
+            root.spawn(Text::new(format!("{} gold", run_stats.gold)));
+
+            spawn_menu_button(
+                root,
+                "Restart",
+                MenuButton { row: 0, column: 0 },
+                GameOverButton::Restart,
+            );
+            spawn_menu_button(
+                root,
+                "Main Menu",
+                MenuButton { row: 1, column: 0 },
+                GameOverButton::MainMenu,
+            );
+        });
+}
+
+fn handle_game_over_buttons(
+    mut activated_events: EventReader<MenuButtonActivated>,
+    button_query: Query<&GameOverButton>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in activated_events.read() {
+        match button_query.get(event.0) {
+            // leaving the run despawns everything scoped to it before the next one starts
+            Ok(GameOverButton::Restart) => next_state.set(GameState::Initializing),
+            Ok(GameOverButton::MainMenu) => next_state.set(GameState::MainMenu),
+            Err(_) => {}
+        }
+    }
+}
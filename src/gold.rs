@@ -0,0 +1,71 @@
+use crate::audio::{PlaySound, SoundEffect};
+use crate::config::Config;
+use crate::game_over::RunStats;
+use crate::particles::ParticleEmitter;
+use crate::player::Player;
+use crate::resources::GlobalTextureAtlas;
+use crate::state::{run_scoped, GameState, InRun};
+use bevy::prelude::*;
+
+/// Tints the coin sprite, which is drawn plain white.
+const GOLD_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+
+/// Dropped by some enemies and picked up like XP, adding to the gold kept between runs.
+#[derive(Component)]
+#[require(StateScoped<InRun>(run_scoped))]
+pub struct GoldCoin;
+
+impl GoldCoin {
+    pub fn spawn(
+        commands: &mut Commands,
+        translation: Vec3,
+        texture_handle: &GlobalTextureAtlas,
+        config: &Config,
+    ) {
+        let mut sprite = texture_handle.sprite("gold_coin");
+        sprite.color = GOLD_COLOR;
+        commands.spawn((
+            GoldCoin,
+            sprite,
+            Transform::from_translation(translation)
+                .with_scale(Vec3::splat(config.sprite.sprite_scale_factor)),
+        ));
+    }
+}
+
+pub struct GoldPlugin;
+
+impl Plugin for GoldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            handle_player_pickup_gold.run_if(in_state(GameState::Gaming)),
+        );
+    }
+}
+
+fn handle_player_pickup_gold(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut run_stats: ResMut<RunStats>,
+    player_query: Query<(&Transform, &Player), With<Player>>,
+    coin_query: Query<(&Transform, Entity), With<GoldCoin>>,
+) {
+    for (player_transform, player) in player_query.iter() {
+        for (coin_transform, coin_entity) in coin_query.iter() {
+            if player_transform
+                .translation
+                .distance_squared(coin_transform.translation)
+                <= 2500.0 * player.xp_ball_pickup_range_multiplier
+            {
+                run_stats.gold += config.enemy.gold_per_coin;
+                commands.trigger(PlaySound(SoundEffect::XpPickup));
+                commands.spawn((
+                    ParticleEmitter::xp_pickup(),
+                    Transform::from_translation(coin_transform.translation),
+                ));
+                commands.entity(coin_entity).despawn();
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use crate::game_over::RunStats;
+use crate::replay::ReplayFinished;
+use crate::state::GameState;
+use bevy::prelude::*;
+
+/// Exits once the run or replay is over, since there is nobody to click through the menus.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::GameOver), exit_after_run)
+            .add_observer(exit_after_replay);
+    }
+}
+
+fn exit_after_run(run_stats: Res<RunStats>, exit: EventWriter<AppExit>) {
+    report_and_exit(&run_stats, exit);
+}
+
+fn exit_after_replay(
+    _trigger: Trigger<ReplayFinished>,
+    run_stats: Res<RunStats>,
+    exit: EventWriter<AppExit>,
+) {
+    report_and_exit(&run_stats, exit);
+}
+
+fn report_and_exit(run_stats: &RunStats, mut exit: EventWriter<AppExit>) {
+    info!(
+        "Survived {} with {} kills and {} gold",
+        run_stats.time_survived_label(),
+        run_stats.kills,
+        run_stats.gold
+    );
+    exit.send(AppExit::Success);
+}
@@ -0,0 +1,267 @@
+use crate::history::{RunHistory, RunRecord};
+use crate::input::{Action, ActionInput};
+use crate::menu::{spawn_menu_button, MenuButton, MenuButtonActivated, MenuSet};
+use crate::settings::user_file;
+use crate::state::GameState;
+use bevy::prelude::*;
+use std::cmp::Reverse;
+
+/// Runs listed at once, the best or most recent first.
+const SHOWN_RUNS: usize = 10;
+const COLUMN_WIDTHS: [f32; 7] = [40.0, 120.0, 100.0, 80.0, 70.0, 70.0, 110.0];
+
+/// What the table is ordered by.
+#[derive(Resource, Clone, Copy, Default, Eq, PartialEq)]
+enum HistorySort {
+    #[default]
+    Time,
+    Level,
+    Kills,
+    Recent,
+}
+
+impl HistorySort {
+    const ALL: [HistorySort; 4] = [
+        HistorySort::Time,
+        HistorySort::Level,
+        HistorySort::Kills,
+        HistorySort::Recent,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            HistorySort::Time => "Time",
+            HistorySort::Level => "Level",
+            HistorySort::Kills => "Kills",
+            HistorySort::Recent => "Recent",
+        }
+    }
+
+    fn sort(self, runs: &[RunRecord]) -> Vec<&RunRecord> {
+        let mut sorted: Vec<&RunRecord> = runs.iter().collect();
+        match self {
+            HistorySort::Time => sorted.sort_by(|a, b| b.duration.total_cmp(&a.duration)),
+            HistorySort::Level => sorted.sort_by(|a, b| {
+                b.level
+                    .cmp(&a.level)
+                    .then(b.duration.total_cmp(&a.duration))
+            }),
+            HistorySort::Kills => sorted.sort_by_key(|run| Reverse(run.kills)),
+            HistorySort::Recent => sorted.reverse(),
+        }
+        sorted
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+enum HighScoresButton {
+    Sort(HistorySort),
+    Export,
+    Back,
+}
+
+#[derive(Component)]
+struct HistoryTable;
+
+#[derive(Component)]
+struct ExportStatus;
+
+pub struct HighScoresPlugin;
+
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::HighScores), spawn_high_scores)
+            .add_systems(
+                Update,
+                (
+                    handle_high_scores_buttons.after(MenuSet),
+                    close_high_scores,
+                    fill_history_table
+                        .after(handle_high_scores_buttons)
+                        .run_if(resource_changed::<HistorySort>),
+                )
+                    .run_if(in_state(GameState::HighScores)),
+            );
+    }
+}
+
+fn spawn_high_scores(mut commands: Commands) {
+    // starts on the best times every visit, and the table is filled in once it exists
+    commands.insert_resource(HistorySort::default());
+
+    commands
+        .spawn((
+            StateScoped(GameState::HighScores),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new("High Scores"),
+                TextFont {
+                    font_size: 36.0,
+                    ..default()
+                },
+            ));
+
+            root.spawn(Node {
+                column_gap: Val::Px(8.0),
+                ..default()
+            })
+            .with_children(|row| {
+                for (column, sort) in HistorySort::ALL.into_iter().enumerate() {
+                    spawn_menu_button(
+                        row,
+                        sort.label(),
+                        MenuButton { row: 0, column },
+                        HighScoresButton::Sort(sort),
+                    );
+                }
+            });
+
+            root.spawn((
+                HistoryTable,
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(2.0),
+                    min_height: Val::Px(300.0),
+                    ..default()
+                },
+            ));
+
+            root.spawn(Node {
+                column_gap: Val::Px(8.0),
+                ..default()
+            })
+            .with_children(|row| {
+                spawn_menu_button(
+                    row,
+                    "Export",
+                    MenuButton { row: 1, column: 0 },
+                    HighScoresButton::Export,
+                );
+                spawn_menu_button(
+                    row,
+                    "Back",
+                    MenuButton { row: 1, column: 1 },
+                    HighScoresButton::Back,
+                );
+            });
+
+            root.spawn((ExportStatus, Text::new("")));
+        });
+}
+
+fn close_high_scores(actions: ActionInput, mut next_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Cancel) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn handle_high_scores_buttons(
+    history: Res<RunHistory>,
+    mut activated_events: EventReader<MenuButtonActivated>,
+    button_query: Query<&HighScoresButton>,
+    mut sort: ResMut<HistorySort>,
+    mut status_query: Query<&mut Text, With<ExportStatus>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in activated_events.read() {
+        match button_query.get(event.0) {
+            Ok(HighScoresButton::Sort(by)) => {
+                sort.set_if_neq(*by);
+            }
+            Ok(HighScoresButton::Export) => {
+                let status = ["history.csv", "history.json"]
+                    .map(|name| {
+                        let path = user_file(name);
+                        match history.export(&path) {
+                            Ok(()) => format!("Exported to {}", path.display()),
+                            Err(err) => {
+                                warn!("Cannot export the run history: {err}");
+                                format!("Cannot export {name}")
+                            }
+                        }
+                    })
+                    .join("\n");
+                for mut text in status_query.iter_mut() {
+                    text.0 = status.clone();
+                }
+            }
+            Ok(HighScoresButton::Back) => next_state.set(GameState::MainMenu),
+            Err(_) => {}
+        }
+    }
+}
+
+fn fill_history_table(
+    mut commands: Commands,
+    history: Res<RunHistory>,
+    sort: Res<HistorySort>,
+    table_query: Query<Entity, With<HistoryTable>>,
+) {
+    let header = [
+        "#",
+        "Character",
+        "Difficulty",
+        "Time",
+        "Level",
+        "Kills",
+        "Killed by",
+    ]
+    .map(str::to_string);
+    let rows: Vec<[String; 7]> = sort
+        .sort(&history.runs)
+        .into_iter()
+        .take(SHOWN_RUNS)
+        .enumerate()
+        .map(|(rank, run)| {
+            [
+                (rank + 1).to_string(),
+                run.character.clone(),
+                format!("{:?}", run.difficulty),
+                run.duration_label(),
+                run.level.to_string(),
+                run.kills.to_string(),
+                run.killed_by.map_or("-", |enemy| enemy.label()).to_string(),
+            ]
+        })
+        .collect();
+
+    for table in table_query.iter() {
+        commands
+            .entity(table)
+            .despawn_descendants()
+            .with_children(|table| {
+                if rows.is_empty() {
+                    table.spawn(Text::new("No finished runs yet"));
+                    return;
+                }
+                for cells in std::iter::once(&header).chain(rows.iter()) {
+                    table.spawn(Node::default()).with_children(|row| {
+                        for (cell, width) in cells.iter().zip(COLUMN_WIDTHS) {
+                            row.spawn((
+                                Text::new(cell.clone()),
+                                TextFont {
+                                    font_size: 18.0,
+                                    ..default()
+                                },
+                                Node {
+                                    width: Val::Px(width),
+                                    ..default()
+                                },
+                            ));
+                        }
+                    });
+                }
+            });
+    }
+}
@@ -0,0 +1,248 @@
+use crate::config::{Config, Difficulty};
+use crate::enemy::EnemyKind;
+use crate::game_over::RunStats;
+use crate::player::Player;
+use crate::rng::GameRng;
+use crate::settings::{read_user_toml, write_user_toml};
+use crate::state::GameState;
+use crate::weapon::WeaponKind;
+use bevy::prelude::*;
+use bevy::utils::SystemTime;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const HISTORY_FILE: &str = "history.toml";
+
+/// One finished run, as kept in the history and exported for balance analysis.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// Seconds since the Unix epoch when the player died.
+    pub finished_at: u64,
+    pub character: String,
+    pub difficulty: Difficulty,
+    pub seed: u64,
+    /// Seconds survived.
+    pub duration: f32,
+    pub level: u32,
+    pub kills: u32,
+    pub kills_by_enemy: BTreeMap<EnemyKind, u32>,
+    pub damage_by_weapon: BTreeMap<WeaponKind, f32>,
+    pub xp_collected: u32,
+    pub gold: u32,
+    pub killed_by: Option<EnemyKind>,
+}
+
+impl RunRecord {
+    /// Duration as `m:ss`.
+    pub fn duration_label(&self) -> String {
+        let seconds = self.duration as u32;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Every finished run, oldest first.
+#[derive(Resource, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunHistory {
+    pub runs: Vec<RunRecord>,
+}
+
+impl RunHistory {
+    pub fn load() -> Self {
+        read_user_toml(HISTORY_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        write_user_toml(HISTORY_FILE, self);
+    }
+
+    /// Writes the history as JSON if `path` ends in `.json`, and as CSV otherwise.
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let contents = if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            serde_json::to_string_pretty(&self.runs).map_err(|err| err.to_string())?
+        } else {
+            self.to_csv()
+        };
+        fs::write(path, contents).map_err(|err| format!("cannot write {}: {err}", path.display()))
+    }
+
+    /// One row per run, with a kill and damage column for every enemy and weapon so every row
+    /// lines up whatever the run came across.
+    fn to_csv(&self) -> String {
+        let mut header = [
+            "finished_at",
+            "character",
+            "difficulty",
+            "seed",
+            "duration",
+            "level",
+            "kills",
+            "xp_collected",
+            "gold",
+            "killed_by",
+        ]
+        .map(str::to_string)
+        .to_vec();
+        header.extend(
+            EnemyKind::ALL
+                .iter()
+                .map(|enemy| format!("kills_{}", column_name(enemy))),
+        );
+        header.extend(
+            WeaponKind::ALL
+                .iter()
+                .map(|weapon| format!("damage_{}", column_name(weapon))),
+        );
+
+        let mut csv = header.join(",") + "\n";
+        for run in self.runs.iter() {
+            let mut row = vec![
+                run.finished_at.to_string(),
+                csv_field(&run.character),
+                column_name(&run.difficulty),
+                run.seed.to_string(),
+                run.duration.to_string(),
+                run.level.to_string(),
+                run.kills.to_string(),
+                run.xp_collected.to_string(),
+                run.gold.to_string(),
+                run.killed_by
+                    .map(|enemy| column_name(&enemy))
+                    .unwrap_or_default(),
+            ];
+            row.extend(EnemyKind::ALL.iter().map(|enemy| {
+                run.kills_by_enemy
+                    .get(enemy)
+                    .copied()
+                    .unwrap_or_default()
+                    .to_string()
+            }));
+            row.extend(WeaponKind::ALL.iter().map(|weapon| {
+                run.damage_by_weapon
+                    .get(weapon)
+                    .copied()
+                    .unwrap_or_default()
+                    .to_string()
+            }));
+            csv += &(row.join(",") + "\n");
+        }
+        csv
+    }
+}
+
+/// The name an enum value has in the history file, like `chain_lightning`.
+fn column_name(value: &impl Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Adds every run that ends to the history; left out when nobody is playing, like the profile.
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Dying), record_run);
+    }
+}
+
+fn record_run(
+    config: Res<Config>,
+    rng: Res<GameRng>,
+    run_stats: Res<RunStats>,
+    player_query: Query<&Player>,
+    mut history: ResMut<RunHistory>,
+) {
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+
+    history.runs.push(RunRecord {
+        finished_at: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+        character: player.character.clone(),
+        difficulty: config.game.difficulty,
+        seed: rng.seed(),
+        duration: run_stats.time_survived.elapsed_secs(),
+        level: player.level,
+        kills: run_stats.kills,
+        kills_by_enemy: run_stats
+            .kills_by_enemy
+            .iter()
+            .map(|(&enemy, &kills)| (enemy, kills))
+            .collect(),
+        damage_by_weapon: run_stats
+            .damage_by_weapon
+            .iter()
+            .map(|(&weapon, &damage)| (weapon, damage))
+            .collect(),
+        xp_collected: run_stats.xp_collected,
+        gold: run_stats.gold,
+        killed_by: run_stats.last_hit_by,
+    });
+    history.save();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(character: &str) -> RunRecord {
+        RunRecord {
+            finished_at: 1_700_000_000,
+            character: character.to_string(),
+            difficulty: Difficulty::Hard,
+            seed: 42,
+            duration: 65.5,
+            level: 4,
+            kills: 12,
+            kills_by_enemy: BTreeMap::from([(EnemyKind::Goblin, 12)]),
+            damage_by_weapon: BTreeMap::from([(WeaponKind::ChainLightning, 2.5)]),
+            xp_collected: 30,
+            gold: 7,
+            killed_by: Some(EnemyKind::Goblin),
+        }
+    }
+
+    #[test]
+    fn csv_field_quotes_only_when_needed() {
+        assert_eq!(csv_field("Knight"), "Knight");
+        assert_eq!(csv_field("Knight, the Bold"), "\"Knight, the Bold\"");
+        assert_eq!(csv_field("the \"Bold\""), "\"the \"\"Bold\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn to_csv_writes_a_header_and_a_row_per_run() {
+        let history = RunHistory {
+            runs: vec![run("Knight"), run("Knight, the Bold")],
+        };
+        let csv = history.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "finished_at,character,difficulty,seed,duration,level,kills,xp_collected,gold,\
+                 killed_by,kills_goblin,damage_gun,damage_homing_missile,damage_chain_lightning,\
+                 damage_arcane_staff,damage_missile_swarm,damage_thunderstorm",
+                "1700000000,Knight,hard,42,65.5,4,12,30,7,goblin,12,0,0,2.5,0,0,0",
+                "1700000000,\"Knight, the Bold\",hard,42,65.5,4,12,30,7,goblin,12,0,0,2.5,0,0,0",
+            ]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use crate::game_over::RunStats;
+use crate::player::{Player, StatUpgrade};
+use crate::state::{GameState, InRun};
+use crate::weapon::Weapon;
+use bevy::color::palettes::tailwind;
+use bevy::prelude::*;
+
+/// The root of the HUD, which lasts from the first frame of play to the end of the run.
+#[derive(Component)]
+struct Hud;
+
+#[derive(Component, Clone, Copy)]
+enum HudText {
+    Timer,
+    Level,
+    Kills,
+    Gold,
+    Health,
+    Loadout,
+}
+
+/// The coloured part of a bar, sized as a percentage of its track.
+#[derive(Component, Clone, Copy)]
+enum HudBar {
+    Health,
+    Xp,
+}
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Gaming), spawn_hud)
+            .add_systems(
+                Update,
+                (update_hud_text, update_hud_bars).run_if(in_state(GameState::Gaming)),
+            );
+    }
+}
+
+fn spawn_hud(mut commands: Commands, hud_query: Query<(), With<Hud>>) {
+    // coming back from the pause menu finds the HUD still there
+    if !hud_query.is_empty() {
+        return;
+    }
+
+    commands
+        .spawn((
+            Hud,
+            StateScoped(InRun),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::SpaceBetween,
+                ..default()
+            },
+        ))
+        .with_children(|root| {
+            root.spawn(Node {
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            })
+            .with_children(|top| {
+                spawn_bar(top, HudBar::Xp, tailwind::BLUE_600.into());
+
+                top.spawn(Node {
+                    width: Val::Percent(100.0),
+                    padding: UiRect::axes(Val::Px(12.0), Val::Px(4.0)),
+                    justify_content: JustifyContent::SpaceBetween,
+                    ..default()
+                })
+                .with_children(|row| {
+                    spawn_text(row, HudText::Level, 22.0);
+                    spawn_text(row, HudText::Timer, 28.0);
+                    row.spawn(Node {
+                        column_gap: Val::Px(16.0),
+                        ..default()
+                    })
+                    .with_children(|counters| {
+                        spawn_text(counters, HudText::Kills, 22.0);
+                        spawn_text(counters, HudText::Gold, 22.0);
+                    });
+                });
+            });
+
+            root.spawn(Node {
+                width: Val::Percent(100.0),
+                padding: UiRect::all(Val::Px(12.0)),
+                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::FlexEnd,
+                ..default()
+            })
+            .with_children(|bottom| {
+                bottom
+                    .spawn(Node {
+                        width: Val::Percent(30.0),
+                        max_width: Val::Px(320.0),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.0),
+                        ..default()
+                    })
+                    .with_children(|health| {
+                        spawn_text(health, HudText::Health, 20.0);
+                        spawn_bar(health, HudBar::Health, tailwind::RED_500.into());
+                    });
+                spawn_text(bottom, HudText::Loadout, 18.0);
+            });
+        });
+}
+
+fn spawn_text(parent: &mut ChildBuilder, hud_text: HudText, font_size: f32) {
+    parent.spawn((
+        hud_text,
+        Text::new(""),
+        TextFont {
+            font_size,
+            ..default()
+        },
+    ));
+}
+
+fn spawn_bar(parent: &mut ChildBuilder, hud_bar: HudBar, color: Color) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(tailwind::NEUTRAL_800.into()),
+        ))
+        .with_children(|track| {
+            track.spawn((
+                hud_bar,
+                Node {
+                    width: Val::Percent(0.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                BackgroundColor(color),
+            ));
+        });
+}
+
+fn update_hud_text(
+    run_stats: Res<RunStats>,
+    player_query: Query<&Player>,
+    weapon_query: Query<&Weapon>,
+    mut text_query: Query<(&HudText, &mut Text)>,
+) {
+    if player_query.is_empty() {
+        return;
+    }
+
+    let player = player_query.single();
+    for (hud_text, mut text) in text_query.iter_mut() {
+        let value = match hud_text {
+            HudText::Timer => run_stats.time_survived_label(),
+            HudText::Level => format!("Level {}", player.level),
+            HudText::Kills => format!("Kills {}", run_stats.kills),
+            HudText::Gold => format!("Gold {}", run_stats.gold),
+            HudText::Health => format!(
+                "HP {:.0} / {:.0}",
+                player.health.max(0.0) * 100.0,
+                player.max_health * 100.0
+            ),
+            HudText::Loadout => loadout(player, &weapon_query),
+        };
+        if text.0 != value {
+            text.0 = value;
+        }
+    }
+}
+
+/// One line per weapon, then one per passive stat upgrade taken so far.
+fn loadout(player: &Player, weapon_query: &Query<&Weapon>) -> String {
+    let mut weapons: Vec<&Weapon> = weapon_query.iter().collect();
+    weapons.sort_by_key(|weapon| weapon.kind);
+
+    let weapon_lines = weapons
+        .into_iter()
+        .map(|weapon| format!("{} Lv {}", weapon.kind.label(), weapon.level));
+    let passive_lines = StatUpgrade::ALL.into_iter().filter_map(|upgrade| {
+        let count = player.stat_upgrade_count(upgrade);
+        (count > 0).then(|| format!("{} Lv {count}", upgrade.label()))
+    });
+    weapon_lines
+        .chain(passive_lines)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn update_hud_bars(player_query: Query<&Player>, mut bar_query: Query<(&HudBar, &mut Node)>) {
+    if player_query.is_empty() {
+        return;
+    }
+
+    let player = player_query.single();
+    for (hud_bar, mut node) in bar_query.iter_mut() {
+        let fraction = match hud_bar {
+            HudBar::Health => player.health / player.max_health,
+            HudBar::Xp => player.xp as f32 / player.xp_to_next_level() as f32,
+        };
+        node.width = Val::Percent(fraction.clamp(0.0, 1.0) * 100.0);
+    }
+}
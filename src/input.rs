@@ -0,0 +1,493 @@
+use crate::replay::{InputFrame, Replay};
+use crate::settings::Settings;
+use bevy::ecs::system::SystemParam;
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub struct PlayerInputPlugin;
+
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, log_gamepad_connections);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Dash,
+    Pause,
+    Confirm,
+    Cancel,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Fire,
+        Action::Dash,
+        Action::Pause,
+        Action::Confirm,
+        Action::Cancel,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move Up",
+            Action::MoveDown => "Move Down",
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Fire => "Fire",
+            Action::Dash => "Dash",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Cancel => "Cancel",
+        }
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        match self {
+            Action::MoveUp => vec![
+                Binding::Key(KeyCode::KeyW),
+                Binding::Gamepad(GamepadButton::DPadUp),
+            ],
+            Action::MoveDown => vec![
+                Binding::Key(KeyCode::KeyS),
+                Binding::Gamepad(GamepadButton::DPadDown),
+            ],
+            Action::MoveLeft => vec![
+                Binding::Key(KeyCode::KeyA),
+                Binding::Gamepad(GamepadButton::DPadLeft),
+            ],
+            Action::MoveRight => vec![
+                Binding::Key(KeyCode::KeyD),
+                Binding::Gamepad(GamepadButton::DPadRight),
+            ],
+            Action::Fire => vec![
+                Binding::Mouse(MouseButton::Left),
+                Binding::Gamepad(GamepadButton::RightTrigger2),
+            ],
+            Action::Dash => vec![
+                Binding::Key(KeyCode::Space),
+                Binding::Gamepad(GamepadButton::LeftTrigger2),
+            ],
+            Action::Pause => vec![
+                Binding::Key(KeyCode::Escape),
+                Binding::Gamepad(GamepadButton::Start),
+            ],
+            Action::Confirm => vec![
+                Binding::Key(KeyCode::Enter),
+                Binding::Gamepad(GamepadButton::South),
+            ],
+            Action::Cancel => vec![
+                Binding::Key(KeyCode::Escape),
+                Binding::Gamepad(GamepadButton::East),
+            ],
+        }
+    }
+
+    fn in_gameplay(self) -> bool {
+        !matches!(self, Action::Confirm | Action::Cancel)
+    }
+
+    fn in_menus(self) -> bool {
+        !matches!(self, Action::Fire | Action::Dash | Action::Pause)
+    }
+
+    /// Two actions may share a binding only if they are never read at the same time.
+    fn conflicts_with(self, other: Action) -> bool {
+        self != other
+            && ((self.in_gameplay() && other.in_gameplay())
+                || (self.in_menus() && other.in_menus()))
+    }
+}
+
+macro_rules! named_buttons {
+    ($button:ident: $($name:ident),* $(,)?) => {
+        &[$((stringify!($name), $button::$name)),*]
+    };
+}
+
+const KEYS: &[(&str, KeyCode)] = named_buttons!(KeyCode:
+    KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ, KeyK, KeyL, KeyM,
+    KeyN, KeyO, KeyP, KeyQ, KeyR, KeyS, KeyT, KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
+    Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    ArrowUp, ArrowDown, ArrowLeft, ArrowRight,
+    Space, Enter, Escape, Tab, Backspace, CapsLock,
+    ShiftLeft, ShiftRight, ControlLeft, ControlRight, AltLeft, AltRight,
+    Minus, Equal, BracketLeft, BracketRight, Backslash, Semicolon, Quote,
+    Comma, Period, Slash, Backquote,
+    Insert, Delete, Home, End, PageUp, PageDown,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadDecimal, NumpadEnter,
+);
+
+const MOUSE_BUTTONS: &[(&str, MouseButton)] =
+    named_buttons!(MouseButton: Left, Right, Middle, Back, Forward);
+
+const GAMEPAD_BUTTONS: &[(&str, GamepadButton)] = named_buttons!(GamepadButton:
+    South, East, North, West, C, Z,
+    LeftTrigger, LeftTrigger2, RightTrigger, RightTrigger2,
+    Select, Start, Mode, LeftThumb, RightThumb,
+    DPadUp, DPadDown, DPadLeft, DPadRight,
+);
+
+fn name_of<T: PartialEq>(table: &[(&'static str, T)], button: &T) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(_, candidate)| candidate == button)
+        .map(|(name, _)| *name)
+}
+
+fn button_named<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(candidate, _)| *candidate == name)
+        .map(|(_, button)| *button)
+}
+
+/// A physical input an [`Action`] can be bound to.
+///
+/// Stored in the settings file as `key:KeyW`, `mouse:Left` or `gamepad:South`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl Binding {
+    /// Whether this binding can be written to the settings file.
+    pub fn is_supported(&self) -> bool {
+        self.name().is_some()
+    }
+
+    fn name(&self) -> Option<&'static str> {
+        match self {
+            Binding::Key(key) => name_of(KEYS, key),
+            Binding::Mouse(button) => name_of(MOUSE_BUTTONS, button),
+            Binding::Gamepad(button) => name_of(GAMEPAD_BUTTONS, button),
+        }
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        let device = match binding {
+            Binding::Key(_) => "key",
+            Binding::Mouse(_) => "mouse",
+            Binding::Gamepad(_) => "gamepad",
+        };
+        format!("{device}:{}", binding.name().unwrap_or("Unknown"))
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (device, name) = value
+            .split_once(':')
+            .ok_or_else(|| format!("binding `{value}` is missing a device prefix"))?;
+        let binding = match device {
+            "key" => button_named(KEYS, name).map(Binding::Key),
+            "mouse" => button_named(MOUSE_BUTTONS, name).map(Binding::Mouse),
+            "gamepad" => button_named(GAMEPAD_BUTTONS, name).map(Binding::Gamepad),
+            _ => return Err(format!("unknown input device `{device}` in `{value}`")),
+        };
+        binding.ok_or_else(|| format!("unknown button `{name}` in `{value}`"))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name().unwrap_or("?");
+        match self {
+            Binding::Key(_) => write!(
+                f,
+                "{}",
+                name.trim_start_matches("Key").trim_start_matches("Digit")
+            ),
+            Binding::Mouse(_) => write!(f, "Mouse {name}"),
+            Binding::Gamepad(_) => write!(f, "Pad {name}"),
+        }
+    }
+}
+
+/// Each action holds up to [`InputBindings::SLOTS`] bindings, any device in any slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InputBindings(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self(
+            Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_bindings()))
+                .collect(),
+        )
+    }
+}
+
+impl InputBindings {
+    pub const SLOTS: usize = 2;
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn slot(&self, action: Action, slot: usize) -> Option<Binding> {
+        self.get(action).get(slot).copied()
+    }
+
+    /// Fills in actions missing from a hand-edited settings file with their defaults.
+    pub fn fill_missing(&mut self) {
+        for action in Action::ALL {
+            self.0
+                .entry(action)
+                .or_insert_with(|| action.default_bindings());
+        }
+    }
+
+    /// The action that would clash with binding `binding` to `action`, if any.
+    pub fn conflict(&self, action: Action, binding: Binding) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|other| action.conflicts_with(*other) && self.get(*other).contains(&binding))
+    }
+
+    /// Puts `binding` in `slot` of `action`, refusing if another action already uses it.
+    pub fn bind(&mut self, action: Action, slot: usize, binding: Binding) -> Result<(), Action> {
+        if let Some(other) = self.conflict(action, binding) {
+            return Err(other);
+        }
+
+        let bindings = self.0.entry(action).or_default();
+        bindings.retain(|existing| *existing != binding);
+        if slot < bindings.len() {
+            bindings[slot] = binding;
+        } else {
+            bindings.push(binding);
+        }
+        Ok(())
+    }
+}
+
+/// Reads [`Action`]s through the bindings in [`Settings`] instead of raw devices.
+///
+/// While a [`Replay`] is playing, gameplay actions come from the recording instead.
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    keyboard: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Query<'w, 's, &'static Gamepad>,
+    settings: Res<'w, Settings>,
+    replay: Option<Res<'w, Replay>>,
+}
+
+impl ActionInput<'_, '_> {
+    fn replayed(&self) -> Option<&InputFrame> {
+        self.replay.as_ref().and_then(|replay| replay.frame())
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        if let Some(frame) = self.replayed() {
+            return frame.pressed(action);
+        }
+        self.settings
+            .bindings
+            .get(action)
+            .iter()
+            .any(|binding| match binding {
+                Binding::Key(key) => self.keyboard.pressed(*key),
+                Binding::Mouse(button) => self.mouse.pressed(*button),
+                Binding::Gamepad(button) => any_gamepad_pressed(&self.gamepads, *button),
+            })
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        if let Some(frame) = self.replayed() {
+            return frame.just_pressed(action);
+        }
+        self.settings
+            .bindings
+            .get(action)
+            .iter()
+            .any(|binding| match binding {
+                Binding::Key(key) => self.keyboard.just_pressed(*key),
+                Binding::Mouse(button) => self.mouse.just_pressed(*button),
+                Binding::Gamepad(button) => self
+                    .gamepads
+                    .iter()
+                    .any(|gamepad| gamepad.just_pressed(*button)),
+            })
+    }
+
+    /// Digital movement from the bound actions, or the left stick while those are idle.
+    pub fn movement(&self) -> Vec2 {
+        if let Some(frame) = self.replayed() {
+            return frame.movement();
+        }
+        let mut delta = Vec2::ZERO;
+        if self.pressed(Action::MoveUp) {
+            delta.y += 1.0;
+        }
+        if self.pressed(Action::MoveDown) {
+            delta.y -= 1.0;
+        }
+        if self.pressed(Action::MoveLeft) {
+            delta.x -= 1.0;
+        }
+        if self.pressed(Action::MoveRight) {
+            delta.x += 1.0;
+        }
+        delta = delta.normalize_or_zero();
+
+        // the stick only takes over while the buttons are idle so the two never stack
+        if delta == Vec2::ZERO {
+            delta = self.stick(Gamepad::left_stick).unwrap_or_default();
+        }
+        delta
+    }
+
+    pub fn stick(&self, stick: fn(&Gamepad) -> Vec2) -> Option<Vec2> {
+        if self.replayed().is_some() {
+            return None;
+        }
+        first_active_stick(
+            self.gamepads.iter().map(stick),
+            self.settings.stick_deadzone,
+        )
+    }
+
+    /// The first supported button pressed this frame on any device, used for rebinding.
+    pub fn any_just_pressed(&self) -> Option<Binding> {
+        let keys = self
+            .keyboard
+            .get_just_pressed()
+            .map(|key| Binding::Key(*key));
+        let mouse_buttons = self
+            .mouse
+            .get_just_pressed()
+            .map(|button| Binding::Mouse(*button));
+        let gamepad_buttons = self
+            .gamepads
+            .iter()
+            .flat_map(|gamepad| gamepad.get_just_pressed())
+            .map(|button| Binding::Gamepad(*button));
+
+        keys.chain(mouse_buttons)
+            .chain(gamepad_buttons)
+            .find(Binding::is_supported)
+    }
+}
+
+/// Picks the first stick pushed past `deadzone`, so any connected pad can take over.
+///
+/// The result is rescaled so the edge of the deadzone maps to zero and full tilt to one.
+pub fn first_active_stick(sticks: impl IntoIterator<Item = Vec2>, deadzone: f32) -> Option<Vec2> {
+    sticks
+        .into_iter()
+        .find(|stick| stick.length() > deadzone)
+        .map(|stick| {
+            let magnitude = ((stick.length() - deadzone) / (1.0 - deadzone)).min(1.0);
+            stick.normalize() * magnitude
+        })
+}
+
+/// Whether any connected pad is holding `button`.
+pub fn any_gamepad_pressed<'a>(
+    gamepads: impl IntoIterator<Item = &'a Gamepad>,
+    button: GamepadButton,
+) -> bool {
+    gamepads.into_iter().any(|gamepad| gamepad.pressed(button))
+}
+
+fn log_gamepad_connections(mut connection_events: EventReader<GamepadConnectionEvent>) {
+    for event in connection_events.read() {
+        match &event.connection {
+            GamepadConnection::Connected { name, .. } => {
+                info!("Gamepad {name} connected as {}", event.gamepad)
+            }
+            GamepadConnection::Disconnected => info!("Gamepad {} disconnected", event.gamepad),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> Result<Binding, String> {
+        Binding::try_from(value.to_string())
+    }
+
+    #[test]
+    fn bindings_parse_from_each_device() {
+        assert_eq!(parse("key:KeyW"), Ok(Binding::Key(KeyCode::KeyW)));
+        assert_eq!(parse("mouse:Left"), Ok(Binding::Mouse(MouseButton::Left)));
+        assert_eq!(
+            parse("gamepad:RightTrigger2"),
+            Ok(Binding::Gamepad(GamepadButton::RightTrigger2))
+        );
+    }
+
+    #[test]
+    fn every_named_button_round_trips() {
+        let bindings = KEYS
+            .iter()
+            .map(|(_, key)| Binding::Key(*key))
+            .chain(
+                MOUSE_BUTTONS
+                    .iter()
+                    .map(|(_, button)| Binding::Mouse(*button)),
+            )
+            .chain(
+                GAMEPAD_BUTTONS
+                    .iter()
+                    .map(|(_, button)| Binding::Gamepad(*button)),
+            );
+        for binding in bindings {
+            assert_eq!(parse(&String::from(binding)), Ok(binding));
+        }
+    }
+
+    #[test]
+    fn malformed_bindings_are_rejected() {
+        assert_eq!(
+            parse("KeyW"),
+            Err("binding `KeyW` is missing a device prefix".to_string())
+        );
+        assert_eq!(
+            parse("joystick:South"),
+            Err("unknown input device `joystick` in `joystick:South`".to_string())
+        );
+        assert_eq!(
+            parse("key:Hyper"),
+            Err("unknown button `Hyper` in `key:Hyper`".to_string())
+        );
+        assert!(parse("mouse:South").is_err());
+    }
+
+    #[test]
+    fn missing_actions_are_filled_with_defaults() {
+        let mut bindings: InputBindings = toml::from_str("fire = [\"key:KeyF\"]").unwrap();
+        bindings.fill_missing();
+        assert_eq!(bindings.get(Action::Fire), [Binding::Key(KeyCode::KeyF)]);
+        assert_eq!(bindings.get(Action::Dash), Action::Dash.default_bindings());
+    }
+}
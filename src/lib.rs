@@ -1,12 +1,34 @@
 pub mod animation;
+pub mod audio;
 pub mod camera;
+pub mod character_select;
+pub mod cli;
 pub mod collision;
 pub mod config;
 pub mod enemy;
+pub mod feedback;
+pub mod game_over;
+pub mod gold;
+pub mod headless;
+pub mod high_scores;
+pub mod history;
+pub mod hud;
+pub mod input;
+pub mod main_menu;
+pub mod menu;
+pub mod particles;
+pub mod pause_menu;
 pub mod player;
+pub mod profile;
+pub mod replay;
 pub mod resources;
+pub mod rng;
+pub mod save;
+pub mod settings;
+pub mod settings_menu;
+pub mod shop;
+pub mod sprites;
 pub mod state;
-pub mod util;
 pub mod weapon;
 pub mod world;
 pub mod xp_ball;
@@ -1,40 +1,227 @@
+use bevy::app::{PluginGroupBuilder, ScheduleRunnerPlugin};
 use bevy::prelude::*;
+use bevy::render::settings::WgpuSettings;
+use bevy::render::RenderPlugin;
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
+use clap::Parser;
 use cs415_project::animation::AnimationPlugin;
+use cs415_project::audio::AudioPlugin;
 use cs415_project::camera::CameraPlugin;
+use cs415_project::character_select::CharacterSelectPlugin;
+use cs415_project::cli::Cli;
 use cs415_project::collision::CollisionPlugin;
-use cs415_project::config::CONFIG;
+use cs415_project::config::{ConfigPlugin, ConfigSource};
 use cs415_project::enemy::EnemyPlugin;
+use cs415_project::feedback::FeedbackPlugin;
+use cs415_project::game_over::GameOverPlugin;
+use cs415_project::gold::GoldPlugin;
+use cs415_project::headless::HeadlessPlugin;
+use cs415_project::high_scores::HighScoresPlugin;
+use cs415_project::history::{HistoryPlugin, RunHistory};
+use cs415_project::hud::HudPlugin;
+use cs415_project::input::PlayerInputPlugin;
+use cs415_project::main_menu::MainMenuPlugin;
+use cs415_project::menu::MenuPlugin;
+use cs415_project::particles::ParticlePlugin;
+use cs415_project::pause_menu::PauseMenuPlugin;
 use cs415_project::player::PlayerPlugin;
+use cs415_project::profile::{Profile, ProfilePlugin};
+use cs415_project::replay::{Recorder, Recording, Replay, ReplayPlugin};
 use cs415_project::resources::ResourcesPlugin;
-use cs415_project::state::GameState;
+use cs415_project::rng::{GameRng, RngPlugin};
+use cs415_project::save::SavePlugin;
+use cs415_project::settings::{DisplaySettings, Settings, SettingsPlugin};
+use cs415_project::settings_menu::SettingsMenuPlugin;
+use cs415_project::shop::ShopPlugin;
+use cs415_project::sprites::SpriteManifestPlugin;
+use cs415_project::state::{GameState, InRun, InSettings, StartState};
 use cs415_project::weapon::WeaponPlugin;
 use cs415_project::world::WorldPlugin;
 use cs415_project::xp_ball::XPBallPlugin;
+use std::path::Path;
+use std::time::Duration;
 
 fn main() {
-    App::new()
-        .add_plugins(
-            DefaultPlugins
-                .set(ImagePlugin::default_nearest())
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        resizable: true,
-                        focused: true,
-                        resolution: (CONFIG.app.window_height, CONFIG.app.window_width).into(),
-                        ..default()
-                    }),
-                    ..default()
-                }),
-        )
+    let cli = Cli::parse();
+    if let Some(path) = &cli.check_config {
+        let source = ConfigSource {
+            path: path.clone().or(cli.config.clone()),
+            difficulty: cli.difficulty,
+        };
+        std::process::exit(check_config(&source));
+    }
+    if let Some(path) = &cli.export_history {
+        std::process::exit(export_history(path));
+    }
+
+    let recording = cli.replay.as_deref().map(|path| {
+        Recording::load(path).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            std::process::exit(1)
+        })
+    });
+    // report a broken config before a window opens, not the first time a value is needed
+    let config_source = cli.config_source(recording.as_ref());
+    let config = config_source.load().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1)
+    });
+    let chosen_character = cli.character(recording.as_ref());
+    if let Some(name) = &chosen_character.0 {
+        if !config
+            .characters
+            .iter()
+            .any(|character| &character.name == name)
+        {
+            eprintln!("There is no character called {name} in the config");
+            std::process::exit(1);
+        }
+    }
+    let settings = Settings::load();
+    // a replay starts with the upgrades it was recorded with, whatever has been bought since
+    let profile = match &recording {
+        Some(recording) => Profile {
+            upgrades: recording.upgrades.clone(),
+            ..default()
+        },
+        None => Profile::load(),
+    };
+    let rng = GameRng::new(
+        recording
+            .as_ref()
+            .map(|recording| recording.seed)
+            .or(cli.seed),
+    );
+
+    let mut app = App::new();
+    app.add_plugins(default_plugins(&cli, cli.display(settings.display)))
+        .insert_resource(config_source)
+        .insert_resource(config)
+        .insert_resource(settings)
+        .insert_resource(profile)
+        .insert_resource(RunHistory::load())
+        .insert_resource(chosen_character)
+        .insert_resource(rng)
+        .insert_resource(StartState(cli.start_state()))
+        .init_state::<GameState>()
+        .add_computed_state::<InRun>()
+        .add_computed_state::<InSettings>()
+        .enable_state_scoped_entities::<GameState>()
+        .enable_state_scoped_entities::<InRun>()
+        .enable_state_scoped_entities::<InSettings>()
         .add_plugins(AnimationPlugin)
+        .add_plugins(AudioPlugin)
         .add_plugins(CameraPlugin)
+        .add_plugins(CharacterSelectPlugin)
         .add_plugins(CollisionPlugin)
+        .add_plugins(ConfigPlugin)
         .add_plugins(EnemyPlugin)
+        .add_plugins(FeedbackPlugin)
+        .add_plugins(GameOverPlugin)
+        .add_plugins(GoldPlugin)
+        .add_plugins(HighScoresPlugin)
+        .add_plugins(HudPlugin)
+        .add_plugins(MainMenuPlugin)
+        .add_plugins(MenuPlugin)
+        .add_plugins(ParticlePlugin)
+        .add_plugins(PauseMenuPlugin)
+        .add_plugins(PlayerInputPlugin)
         .add_plugins(PlayerPlugin)
+        .add_plugins(ReplayPlugin)
         .add_plugins(ResourcesPlugin)
+        .add_plugins(RngPlugin)
+        .add_plugins(SettingsPlugin)
+        .add_plugins(SettingsMenuPlugin)
+        .add_plugins(ShopPlugin)
+        .add_plugins(SpriteManifestPlugin)
         .add_plugins(WeaponPlugin)
         .add_plugins(WorldPlugin)
-        .add_plugins(XPBallPlugin)
-        .init_state::<GameState>()
-        .run();
+        .add_plugins(XPBallPlugin);
+
+    if cli.headless {
+        app.add_plugins(HeadlessPlugin);
+    }
+    // nobody is playing headless runs and replays, so they leave the player's progress alone
+    if !cli.headless && recording.is_none() {
+        app.add_plugins(HistoryPlugin)
+            .add_plugins(ProfilePlugin)
+            .add_plugins(SavePlugin);
+    }
+    if let Some(path) = cli.record.clone() {
+        app.insert_resource(Recorder::new(path));
+    }
+    if let Some(recording) = recording {
+        app.insert_resource(Replay::new(recording));
+    }
+    app.run();
+}
+
+fn default_plugins(cli: &Cli, display: DisplaySettings) -> PluginGroupBuilder {
+    let plugins = DefaultPlugins.set(ImagePlugin::default_nearest());
+    if !cli.headless {
+        return plugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                resizable: true,
+                focused: true,
+                resolution: (display.width, display.height).into(),
+                mode: display.window_mode(),
+                present_mode: display.present_mode(),
+                ..default()
+            }),
+            ..default()
+        });
+    }
+
+    // a replay has its frame times recorded, so it can run as fast as the game updates
+    let wait = if cli.replay.is_some() {
+        Duration::ZERO
+    } else {
+        Duration::from_secs_f64(1.0 / 60.0)
+    };
+    plugins
+        .set(WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            ..default()
+        })
+        .set(RenderPlugin {
+            render_creation: WgpuSettings {
+                backends: None,
+                ..default()
+            }
+            .into(),
+            ..default()
+        })
+        .disable::<WinitPlugin>()
+        .add(ScheduleRunnerPlugin::run_loop(wait))
+}
+
+/// Reports every problem in a config file without starting the game.
+fn check_config(source: &ConfigSource) -> i32 {
+    match source.load() {
+        Ok(_) => {
+            println!("Config is valid");
+            0
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            1
+        }
+    }
+}
+
+/// Writes every finished run to `path` for analysis without starting the game.
+fn export_history(path: &str) -> i32 {
+    let history = RunHistory::load();
+    match history.export(Path::new(path)) {
+        Ok(()) => {
+            println!("Exported {} runs to {path}", history.runs.len());
+            0
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            1
+        }
+    }
 }
@@ -0,0 +1,113 @@
+use crate::menu::{spawn_menu_button, MenuButton, MenuButtonActivated, MenuSet};
+use crate::save::{ResumeRun, SaveSlot};
+use crate::state::GameState;
+use bevy::prelude::*;
+
+#[derive(Component, Clone, Copy)]
+enum MainMenuButton {
+    Continue,
+    Play,
+    Shop,
+    HighScores,
+    Settings,
+    Quit,
+}
+
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+            .add_systems(
+                Update,
+                handle_main_menu_buttons
+                    .after(MenuSet)
+                    .run_if(in_state(GameState::MainMenu)),
+            );
+    }
+}
+
+fn spawn_main_menu(mut commands: Commands, slot: Option<Res<SaveSlot>>) {
+    // a save that cannot be resumed is pointed out instead of quietly hidden, with the reason logged
+    let (can_continue, save_unusable) = match slot.map(|slot| slot.load()) {
+        Some(Ok(saved_run)) => (saved_run.is_some(), false),
+        Some(Err(err)) => {
+            warn!("Cannot resume the saved run: {err}");
+            (false, true)
+        }
+        None => (false, false),
+    };
+
+    commands
+        .spawn((
+            StateScoped(GameState::MainMenu),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new("Survivors"),
+                TextFont {
+                    font_size: 64.0,
+                    ..default()
+                },
+            ));
+
+            let buttons = [
+                ("Continue", MainMenuButton::Continue),
+                ("Play", MainMenuButton::Play),
+                ("Shop", MainMenuButton::Shop),
+                ("High Scores", MainMenuButton::HighScores),
+                ("Settings", MainMenuButton::Settings),
+                ("Quit", MainMenuButton::Quit),
+            ];
+            for (row, (label, button)) in buttons
+                .into_iter()
+                .skip(if can_continue { 0 } else { 1 })
+                .enumerate()
+            {
+                spawn_menu_button(root, label, MenuButton { row, column: 0 }, button);
+            }
+
+            if save_unusable {
+                root.spawn(Text::new("The saved run cannot be resumed"));
+            }
+        });
+}
+
+fn handle_main_menu_buttons(
+    mut commands: Commands,
+    mut activated_events: EventReader<MenuButtonActivated>,
+    button_query: Query<&MainMenuButton>,
+    slot: Option<Res<SaveSlot>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    for event in activated_events.read() {
+        match button_query.get(event.0) {
+            Ok(MainMenuButton::Continue) => match slot.as_ref().map(|slot| slot.load()) {
+                Some(Ok(Some(saved_run))) => {
+                    commands.insert_resource(ResumeRun(saved_run));
+                    next_state.set(GameState::Initializing);
+                }
+                Some(Err(err)) => warn!("Cannot resume the saved run: {err}"),
+                _ => {}
+            },
+            Ok(MainMenuButton::Play) => next_state.set(GameState::CharacterSelect),
+            Ok(MainMenuButton::Shop) => next_state.set(GameState::Shop),
+            Ok(MainMenuButton::HighScores) => next_state.set(GameState::HighScores),
+            Ok(MainMenuButton::Settings) => next_state.set(GameState::Settings { in_run: false }),
+            Ok(MainMenuButton::Quit) => {
+                app_exit_events.send(AppExit::Success);
+            }
+            Err(_) => {}
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::input::{Action, ActionInput};
+use bevy::prelude::*;
+
+const BUTTON_COLOR: Color = Color::srgb(0.2, 0.2, 0.22);
+const FOCUSED_BUTTON_COLOR: Color = Color::srgb(0.45, 0.32, 0.12);
+
+/// A focusable button in whichever menu is on screen, laid out on a row/column grid.
+#[derive(Component, Clone, Copy)]
+pub struct MenuButton {
+    pub row: usize,
+    pub column: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct MenuFocus {
+    pub row: usize,
+    pub column: usize,
+}
+
+/// Sent when a [`MenuButton`] is clicked or confirmed from the keyboard or a gamepad.
+#[derive(Event)]
+pub struct MenuButtonActivated(pub Entity);
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MenuSet;
+
+/// While present, menus ignore input so a screen can grab the next press for itself.
+#[derive(Resource)]
+pub struct MenuInputCaptured;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuFocus>()
+            .add_event::<MenuButtonActivated>()
+            .add_systems(
+                Update,
+                (
+                    reset_menu_focus,
+                    (navigate_menu, activate_menu_button)
+                        .run_if(not(resource_exists::<MenuInputCaptured>)),
+                    highlight_menu_buttons,
+                )
+                    .chain()
+                    .in_set(MenuSet)
+                    .run_if(any_with_component::<MenuButton>),
+            );
+    }
+}
+
+pub fn spawn_menu_button(
+    parent: &mut ChildBuilder,
+    label: impl Into<String>,
+    button: MenuButton,
+    marker: impl Bundle,
+) {
+    parent
+        .spawn((
+            Button,
+            button,
+            marker,
+            Node {
+                min_width: Val::Px(220.0),
+                padding: UiRect::axes(Val::Px(16.0), Val::Px(8.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+        ))
+        .with_children(|button| {
+            button.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 22.0,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn reset_menu_focus(mut focus: ResMut<MenuFocus>, added_query: Query<(), Added<MenuButton>>) {
+    if !added_query.is_empty() {
+        *focus = MenuFocus::default();
+    }
+}
+
+fn navigate_menu(
+    actions: ActionInput,
+    mut focus: ResMut<MenuFocus>,
+    button_query: Query<&MenuButton>,
+) {
+    let rows = button_query
+        .iter()
+        .map(|button| button.row)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let columns_in = |row: usize| {
+        button_query
+            .iter()
+            .filter(|button| button.row == row)
+            .map(|button| button.column + 1)
+            .max()
+            .unwrap_or(1)
+    };
+
+    if actions.just_pressed(Action::MoveDown) {
+        focus.row = (focus.row + 1) % rows;
+    }
+    if actions.just_pressed(Action::MoveUp) {
+        focus.row = (focus.row + rows - 1) % rows;
+    }
+
+    let columns = columns_in(focus.row);
+    if actions.just_pressed(Action::MoveRight) {
+        focus.column = (focus.column + 1) % columns;
+    }
+    if actions.just_pressed(Action::MoveLeft) {
+        focus.column = (focus.column + columns - 1) % columns;
+    }
+    focus.column = focus.column.min(columns - 1);
+}
+
+fn activate_menu_button(
+    actions: ActionInput,
+    mut focus: ResMut<MenuFocus>,
+    interaction_query: Query<(Entity, &Interaction, &MenuButton), Changed<Interaction>>,
+    button_query: Query<(Entity, &MenuButton)>,
+    mut activated_events: EventWriter<MenuButtonActivated>,
+) {
+    // the mouse moves focus too, so keyboard and pointer never disagree about the highlight
+    for (entity, interaction, button) in interaction_query.iter() {
+        match interaction {
+            Interaction::Pressed => {
+                *focus = MenuFocus {
+                    row: button.row,
+                    column: button.column,
+                };
+                activated_events.send(MenuButtonActivated(entity));
+            }
+            Interaction::Hovered => {
+                *focus = MenuFocus {
+                    row: button.row,
+                    column: button.column,
+                };
+            }
+            Interaction::None => {}
+        }
+    }
+
+    if actions.just_pressed(Action::Confirm) {
+        if let Some((entity, _)) = button_query
+            .iter()
+            .find(|(_, button)| button.row == focus.row && button.column == focus.column)
+        {
+            activated_events.send(MenuButtonActivated(entity));
+        }
+    }
+}
+
+fn highlight_menu_buttons(
+    focus: Res<MenuFocus>,
+    mut button_query: Query<(&MenuButton, &mut BackgroundColor)>,
+) {
+    for (button, mut background_color) in button_query.iter_mut() {
+        background_color.0 = if button.row == focus.row && button.column == focus.column {
+            FOCUSED_BUTTON_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+    }
+}
@@ -0,0 +1,234 @@
+use crate::config::Config;
+use crate::resources::GlobalTextureAtlas;
+use crate::state::{run_scoped, GameState, InRun};
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use rand::Rng;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+#[derive(Clone, Copy)]
+pub enum EmitterMode {
+    /// Emits `count` particles at once, then the emitter goes away.
+    Burst(u32),
+    /// Emits `rate` particles per second for `duration` seconds.
+    Continuous { rate: f32, duration: f32 },
+}
+
+/// Spawns short-lived sprites that fly out of its position and fade away.
+#[derive(Component, Clone)]
+#[require(Transform, Visibility, StateScoped<InRun>(run_scoped))]
+pub struct ParticleEmitter {
+    pub mode: EmitterMode,
+    pub sprite: &'static str,
+    pub lifetime: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Angle of the centre of the cone, in radians from the x axis.
+    pub direction: f32,
+    /// Half-angle of the cone, so `PI` emits in every direction.
+    pub spread: f32,
+    pub start_color: Color,
+    pub end_color: Color,
+    pub start_scale: f32,
+    pub end_scale: f32,
+    elapsed: f32,
+    pending: f32,
+}
+
+#[derive(Component)]
+#[require(StateScoped<InRun>(run_scoped))]
+struct Particle {
+    velocity: Vec2,
+    age: Timer,
+    start_color: Color,
+    end_color: Color,
+    start_scale: f32,
+    end_scale: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(mode: EmitterMode) -> Self {
+        Self {
+            mode,
+            sprite: "particle",
+            lifetime: 0.5,
+            min_speed: 50.0,
+            max_speed: 150.0,
+            direction: 0.0,
+            spread: PI,
+            start_color: Color::WHITE,
+            end_color: Color::WHITE.with_alpha(0.0),
+            start_scale: 1.0,
+            end_scale: 0.0,
+            elapsed: 0.0,
+            pending: 0.0,
+        }
+    }
+
+    pub fn enemy_death() -> Self {
+        Self {
+            lifetime: 0.6,
+            min_speed: 60.0,
+            max_speed: 220.0,
+            start_color: Color::srgb(0.8, 0.1, 0.1),
+            end_color: Color::srgba(0.3, 0.0, 0.0, 0.0),
+            start_scale: 1.2,
+            ..Self::new(EmitterMode::Burst(16))
+        }
+    }
+
+    pub fn xp_pickup() -> Self {
+        Self {
+            lifetime: 0.35,
+            min_speed: 30.0,
+            max_speed: 90.0,
+            start_color: Color::srgb(0.3, 0.9, 1.0),
+            end_color: Color::srgba(0.3, 0.9, 1.0, 0.0),
+            start_scale: 0.6,
+            ..Self::new(EmitterMode::Burst(6))
+        }
+    }
+
+    pub fn level_up() -> Self {
+        Self {
+            lifetime: 0.8,
+            min_speed: 80.0,
+            max_speed: 160.0,
+            direction: FRAC_PI_2,
+            spread: PI / 3.0,
+            start_color: Color::srgb(1.0, 0.85, 0.2),
+            end_color: Color::srgba(1.0, 1.0, 0.6, 0.0),
+            ..Self::new(EmitterMode::Continuous {
+                rate: 60.0,
+                duration: 0.6,
+            })
+        }
+    }
+
+    /// Sparks thrown back along `towards`, the side the projectile came from.
+    pub fn projectile_impact(towards: Vec2) -> Self {
+        Self {
+            lifetime: 0.25,
+            min_speed: 100.0,
+            max_speed: 250.0,
+            direction: towards.to_angle(),
+            spread: PI / 4.0,
+            start_color: Color::srgb(1.0, 0.95, 0.7),
+            end_color: Color::srgba(1.0, 0.5, 0.1, 0.0),
+            start_scale: 0.5,
+            ..Self::new(EmitterMode::Burst(5))
+        }
+    }
+
+    fn spawn_particle(
+        &self,
+        commands: &mut Commands,
+        texture_handle: &GlobalTextureAtlas,
+        config: &Config,
+        translation: Vec3,
+    ) {
+        let mut rng = rand::rng();
+        let angle = self.direction + rng.random_range(-self.spread..=self.spread);
+        let speed = rng.random_range(self.min_speed..=self.max_speed);
+        let mut sprite = texture_handle.sprite(self.sprite);
+        sprite.color = self.start_color;
+        let transform = Transform::from_translation(translation).with_scale(Vec3::splat(
+            self.start_scale * config.sprite.sprite_scale_factor,
+        ));
+
+        // emitted after transforms propagate, so the global transform has to be filled in here
+        commands.spawn((
+            Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                age: Timer::from_seconds(self.lifetime, TimerMode::Once),
+                start_color: self.start_color,
+                end_color: self.end_color,
+                start_scale: self.start_scale,
+                end_scale: self.end_scale,
+            },
+            sprite,
+            transform,
+            GlobalTransform::from(transform),
+        ));
+    }
+}
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_particles.run_if(in_state(GameState::Gaming)))
+            .add_systems(
+                PostUpdate,
+                emit_particles
+                    .after(TransformSystem::TransformPropagate)
+                    .run_if(in_state(GameState::Gaming)),
+            );
+    }
+}
+
+fn emit_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<Config>,
+    texture_handle: Res<GlobalTextureAtlas>,
+    mut emitter_query: Query<(&mut ParticleEmitter, &GlobalTransform, Entity)>,
+    particle_query: Query<(), With<Particle>>,
+) {
+    if emitter_query.is_empty() {
+        return;
+    }
+
+    // anything past the budget is dropped rather than queued
+    let mut budget = config
+        .game
+        .max_particles
+        .saturating_sub(particle_query.iter().len());
+
+    for (mut emitter, emitter_transform, entity) in emitter_query.iter_mut() {
+        let count = match emitter.mode {
+            EmitterMode::Burst(count) => {
+                commands.entity(entity).despawn_recursive();
+                count as usize
+            }
+            EmitterMode::Continuous { rate, duration } => {
+                emitter.elapsed += time.delta_secs();
+                if emitter.elapsed >= duration {
+                    commands.entity(entity).despawn_recursive();
+                }
+                emitter.pending += rate * time.delta_secs();
+                let count = emitter.pending.floor();
+                emitter.pending -= count;
+                count as usize
+            }
+        };
+
+        let translation = emitter_transform.translation();
+        for _ in 0..count.min(budget) {
+            emitter.spawn_particle(&mut commands, &texture_handle, &config, translation);
+        }
+        budget = budget.saturating_sub(count);
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<Config>,
+    mut particle_query: Query<(&mut Particle, &mut Transform, &mut Sprite, Entity)>,
+) {
+    for (mut particle, mut transform, mut sprite, entity) in particle_query.iter_mut() {
+        particle.age.tick(time.delta());
+        if particle.age.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let t = particle.age.fraction();
+        transform.translation += (particle.velocity * time.delta_secs()).extend(0.0);
+        transform.scale = Vec3::splat(
+            particle.start_scale.lerp(particle.end_scale, t) * config.sprite.sprite_scale_factor,
+        );
+        sprite.color = particle.start_color.mix(&particle.end_color, t);
+    }
+}
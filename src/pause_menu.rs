@@ -0,0 +1,112 @@
+use crate::input::{Action, ActionInput};
+use crate::menu::{spawn_menu_button, MenuButton, MenuButtonActivated, MenuSet};
+use crate::state::{GameState, InRun};
+use bevy::prelude::*;
+
+#[derive(Component, Clone, Copy)]
+enum PauseMenuButton {
+    Resume,
+    Settings,
+    MainMenu,
+}
+
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::Paused),
+            (pause_virtual_time, spawn_pause_menu),
+        )
+        // settings are opened from the pause menu, so the clock only resumes back in game or
+        // once the run is left behind for the main menu
+        .add_systems(OnEnter(GameState::Gaming), resume_virtual_time)
+        .add_systems(OnExit(InRun), resume_virtual_time)
+        .add_systems(Update, pause_game.run_if(in_state(GameState::Gaming)))
+        .add_systems(
+            Update,
+            (resume_game, handle_pause_menu_buttons.after(MenuSet))
+                .run_if(in_state(GameState::Paused)),
+        );
+    }
+}
+
+fn pause_virtual_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn resume_virtual_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn pause_game(actions: ActionInput, mut next_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Pause) {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn resume_game(actions: ActionInput, mut next_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Pause) || actions.just_pressed(Action::Cancel) {
+        next_state.set(GameState::Gaming);
+    }
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    commands
+        .spawn((
+            StateScoped(GameState::Paused),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new("Paused"),
+                TextFont {
+                    font_size: 48.0,
+                    ..default()
+                },
+            ));
+
+            spawn_menu_button(
+                root,
+                "Resume",
+                MenuButton { row: 0, column: 0 },
+                PauseMenuButton::Resume,
+            );
+            spawn_menu_button(
+                root,
+                "Settings",
+                MenuButton { row: 1, column: 0 },
+                PauseMenuButton::Settings,
+            );
+            spawn_menu_button(
+                root,
+                "Main Menu",
+                MenuButton { row: 2, column: 0 },
+                PauseMenuButton::MainMenu,
+            );
+        });
+}
+
+fn handle_pause_menu_buttons(
+    mut activated_events: EventReader<MenuButtonActivated>,
+    button_query: Query<&PauseMenuButton>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in activated_events.read() {
+        match button_query.get(event.0) {
+            Ok(PauseMenuButton::Resume) => next_state.set(GameState::Gaming),
+            Ok(PauseMenuButton::Settings) => next_state.set(GameState::Settings { in_run: true }),
+            Ok(PauseMenuButton::MainMenu) => next_state.set(GameState::MainMenu),
+            Err(_) => {}
+        }
+    }
+}
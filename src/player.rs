@@ -1,75 +1,190 @@
-use crate::animation::AnimationTimer;
-use crate::config::CONFIG;
+use crate::audio::{PlaySound, SoundEffect};
+use crate::config::{CharacterConfig, Config, PassiveBonus, PlayerConfig};
+use crate::input::{Action, ActionInput};
+use crate::particles::ParticleEmitter;
+use crate::profile::{PermanentUpgrade, Profile};
 use crate::resources::GlobalTextureAtlas;
-use crate::state::GameState;
-use crate::util::get_sprite_index;
+use crate::rng::{GameRng, RngStream};
+use crate::save::ResumeRun;
+use crate::state::{run_scoped, GameState, InRun};
+use crate::weapon::Weapon;
 use bevy::app::{App, Plugin, Update};
-use bevy::color::palettes::tailwind;
-use bevy::input::ButtonInput;
 use bevy::math::Vec2;
 use bevy::prelude::*;
-use bevy::sprite::MeshMaterial2d;
+use bevy::time::Stopwatch;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatUpgrade {
+    MovementSpeed,
+    AttackSpeed,
+    PickupRange,
+}
+
+impl StatUpgrade {
+    pub const ALL: [StatUpgrade; 3] = [
+        StatUpgrade::MovementSpeed,
+        StatUpgrade::AttackSpeed,
+        StatUpgrade::PickupRange,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StatUpgrade::MovementSpeed => "Movement Speed",
+            StatUpgrade::AttackSpeed => "Attack Speed",
+            StatUpgrade::PickupRange => "Pickup Range",
+        }
+    }
+}
+
+/// A stat a character's passive bonus grows with every level.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CharacterStat {
+    Health,
+    Might,
+    MovementSpeed,
+    AttackSpeed,
+    PickupRange,
+}
+
+impl CharacterStat {
+    pub fn label(self) -> &'static str {
+        match self {
+            CharacterStat::Health => "Health",
+            CharacterStat::Might => "Might",
+            CharacterStat::MovementSpeed => "Movement Speed",
+            CharacterStat::AttackSpeed => "Attack Speed",
+            CharacterStat::PickupRange => "Pickup Range",
+        }
+    }
+}
+
+/// The character the next run is played as, by name, falling back to the first in the config.
+#[derive(Resource, Clone, Default)]
+pub struct ChosenCharacter(pub Option<String>);
 
 #[derive(Component)]
+#[require(StateScoped<InRun>(run_scoped))]
 pub struct Player {
+    pub character: String,
     pub xp: u32,
     pub level: u32,
     pub health: f32,
+    pub max_health: f32,
+    pub damage_multiplier: f32,
+    pub revivals: u32,
     pub attack_speed_multiplier: f32,
     pub movement_speed_multiplier: f32,
     pub xp_ball_pickup_range_multiplier: f32,
+    pub stat_upgrades: HashMap<StatUpgrade, u32>,
+    pub dash_timer: Stopwatch,
 }
 
-impl Default for Player {
-    fn default() -> Self {
+impl Player {
+    /// `character` at the start of a run, with the permanent upgrades from `profile` applied.
+    pub fn new(config: &Config, profile: &Profile, character: &CharacterConfig) -> Self {
+        let bonus = |upgrade| 1.0 + profile.bonus(upgrade, &config.shop);
+        let max_health = character.health * bonus(PermanentUpgrade::StartingHealth);
         Self {
+            character: character.name.clone(),
             xp: 0,
             level: 0,
-            health: 1.0,
-            attack_speed_multiplier: 1.0,
-            movement_speed_multiplier: 1.0,
-            xp_ball_pickup_range_multiplier: 1.0,
+            health: max_health,
+            max_health,
+            damage_multiplier: character.damage * bonus(PermanentUpgrade::Might),
+            revivals: profile.bonus(PermanentUpgrade::Revival, &config.shop) as u32,
+            attack_speed_multiplier: character.attack_speed,
+            movement_speed_multiplier: character.movement_speed * bonus(PermanentUpgrade::Speed),
+            xp_ball_pickup_range_multiplier: character.pickup_range
+                * bonus(PermanentUpgrade::Magnet),
+            stat_upgrades: HashMap::new(),
+            dash_timer: ready_dash_timer(&config.player),
+        }
+    }
+
+    pub fn apply_stat_upgrade(&mut self, upgrade: StatUpgrade, config: &PlayerConfig) {
+        match upgrade {
+            StatUpgrade::MovementSpeed => {
+                self.movement_speed_multiplier += config.movement_speed_multiplier_inc
+            }
+            StatUpgrade::AttackSpeed => {
+                self.attack_speed_multiplier += config.attack_speed_multiplier_inc
+            }
+            StatUpgrade::PickupRange => {
+                self.xp_ball_pickup_range_multiplier += config.xp_ball_pickup_range_multiplier_inc
+            }
         }
+        *self.stat_upgrades.entry(upgrade).or_default() += 1;
     }
+
+    pub fn apply_passive(&mut self, passive: &PassiveBonus) {
+        match passive.stat {
+            CharacterStat::Health => {
+                self.max_health += passive.per_level;
+                self.health += passive.per_level;
+            }
+            CharacterStat::Might => self.damage_multiplier += passive.per_level,
+            CharacterStat::MovementSpeed => self.movement_speed_multiplier += passive.per_level,
+            CharacterStat::AttackSpeed => self.attack_speed_multiplier += passive.per_level,
+            CharacterStat::PickupRange => {
+                self.xp_ball_pickup_range_multiplier += passive.per_level
+            }
+        }
+    }
+
+    pub fn xp_to_next_level(&self) -> u32 {
+        5 + self.level * 3
+    }
+
+    pub fn stat_upgrade_count(&self, upgrade: StatUpgrade) -> u32 {
+        self.stat_upgrades.get(&upgrade).copied().unwrap_or_default()
+    }
+}
+
+/// A dash timer that has already cooled down, so the first dash is available immediately.
+fn ready_dash_timer(config: &PlayerConfig) -> Stopwatch {
+    let mut dash_timer = Stopwatch::new();
+    dash_timer.set_elapsed(Duration::from_secs_f32(config.dash_cooldown));
+    dash_timer
 }
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (init_player, init_stat_bars.after(init_player)).run_if(in_state(GameState::Initializing)),
-        )
-        .add_systems(
-            Update,
-            (handle_player_input, check_player_death, handle_player_xp, update_stat_bars)
-                .run_if(in_state(GameState::Gaming)),
-        );
+        app.init_resource::<ChosenCharacter>()
+            .add_systems(
+                Update,
+                init_player
+                    .run_if(in_state(GameState::Initializing))
+                    .run_if(not(resource_exists::<ResumeRun>)),
+            )
+            .add_systems(
+                Update,
+                (handle_player_input, check_player_death, handle_player_xp)
+                    .run_if(in_state(GameState::Gaming)),
+            );
     }
 }
 
 fn init_player(
     mut commands: Commands,
+    config: Res<Config>,
+    profile: Res<Profile>,
+    chosen_character: Res<ChosenCharacter>,
     texture_handle: Res<GlobalTextureAtlas>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
+    let character = config.character(chosen_character.0.as_deref());
     commands.spawn((
-        Player::default(),
-        Sprite {
-            image: texture_handle.image.clone().unwrap(),
-            texture_atlas: Some(TextureAtlas {
-                layout: texture_handle.layout.clone().unwrap(),
-                index: get_sprite_index(0, 0),
-            }),
-            ..default()
-        },
-        Transform::from_scale(Vec3::splat(CONFIG.sprite.sprite_scale_factor)),
-        AnimationTimer(Timer::from_seconds(
-            CONFIG.game.animation_tick_interval,
-            TimerMode::Repeating,
-        )),
+        Player::new(&config, &profile, character),
+        texture_handle.animated_sprite(&character.sprite),
+        Transform::from_scale(Vec3::splat(config.sprite.sprite_scale_factor)),
     ));
 
     next_state.set(GameState::Gaming);
@@ -77,69 +192,66 @@ fn init_player(
 
 fn handle_player_input(
     time: Res<Time>,
-    mut player_query: Query<(&mut Transform, &Player), With<Player>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    config: Res<Config>,
+    mut player_query: Query<(&mut Transform, &mut Player), With<Player>>,
+    actions: ActionInput,
 ) {
     if player_query.is_empty() {
         return;
     }
 
-    let (mut transform, player) = player_query.single_mut();
-    let w_key_pressed = keyboard_input.pressed(KeyCode::KeyW);
-    let a_key_pressed = keyboard_input.pressed(KeyCode::KeyA);
-    let s_key_pressed = keyboard_input.pressed(KeyCode::KeyS);
-    let d_key_pressed = keyboard_input.pressed(KeyCode::KeyD);
+    let (mut transform, mut player) = player_query.single_mut();
+    let delta = actions.movement();
 
-    let mut delta = Vec2::ZERO;
-    if w_key_pressed {
-        delta.y += 1.0;
-    }
-    if s_key_pressed {
-        delta.y -= 1.0;
-    }
-    if a_key_pressed {
-        delta.x -= 1.0;
+    player.dash_timer.tick(time.delta());
+    if actions.just_pressed(Action::Dash)
+        && delta != Vec2::ZERO
+        && player.dash_timer.elapsed_secs() >= config.player.dash_cooldown
+    {
+        player.dash_timer.reset();
     }
-    if d_key_pressed {
-        delta.x += 1.0;
-    }
-    delta = delta.normalize_or_zero();
+    let speed_multiplier = if player.dash_timer.elapsed_secs() < config.player.dash_duration {
+        player.movement_speed_multiplier * config.player.dash_speed_multiplier
+    } else {
+        player.movement_speed_multiplier
+    };
+
     transform.translation.x = if delta.x < 0.0 {
         f32::max(
             transform.translation.x
-                + player.movement_speed_multiplier
+                + speed_multiplier
                     * delta.x
-                    * CONFIG.player.movement_speed
+                    * config.player.movement_speed
                     * time.delta_secs(),
-            -CONFIG.game.world_width,
+            -config.game.world_width,
         )
     } else {
         f32::min(
             transform.translation.x
-                + player.movement_speed_multiplier
+                + speed_multiplier
                     * delta.x
-                    * CONFIG.player.movement_speed
+                    * config.player.movement_speed
                     * time.delta_secs(),
-            CONFIG.game.world_width,
+            config.game.world_width,
         )
     };
     transform.translation.y = if delta.y < 0.0 {
         f32::max(
             transform.translation.y
-                + player.movement_speed_multiplier
+                + speed_multiplier
                     * delta.y
-                    * CONFIG.player.movement_speed
+                    * config.player.movement_speed
                     * time.delta_secs(),
-            -CONFIG.game.world_height,
+            -config.game.world_height,
         )
     } else {
         f32::min(
             transform.translation.y
-                + player.movement_speed_multiplier
+                + speed_multiplier
                     * delta.y
-                    * CONFIG.player.movement_speed
+                    * config.player.movement_speed
                     * time.delta_secs(),
-            CONFIG.game.world_height,
+            config.game.world_height,
         )
     };
 
@@ -147,124 +259,59 @@ fn handle_player_input(
 }
 
 fn check_player_death(
-    player_query: Query<&Player, With<Player>>,
+    mut commands: Commands,
+    config: Res<Config>,
+    mut player_query: Query<(&mut Player, Entity), With<Player>>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    let player = player_query.single();
-    if player.health <= 0.0 {
+    let (mut player, player_entity) = player_query.single_mut();
+    if player.health > 0.0 {
+        return;
+    }
+
+    if player.revivals > 0 {
+        player.revivals -= 1;
+        player.health = player.max_health * config.player.revival_health;
+        commands
+            .entity(player_entity)
+            .with_child(ParticleEmitter::level_up());
+        commands.trigger(PlaySound(SoundEffect::LevelUp));
+        info!("Revived, {} revivals left", player.revivals);
+    } else {
         next_state.set(GameState::Dying);
     }
 }
 
-fn handle_player_xp(mut player_query: Query<&mut Player, With<Player>>) {
-    for mut player in player_query.iter_mut() {
-        if player.xp >= 5 + player.level * 3 {
+fn handle_player_xp(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut player_query: Query<(&mut Player, Entity), With<Player>>,
+    mut weapon_query: Query<&mut Weapon, With<Weapon>>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (mut player, player_entity) in player_query.iter_mut() {
+        if player.xp >= player.xp_to_next_level() {
             player.level += 1;
             player.xp = 0;
-            player.health = (player.health + CONFIG.player.health_per_lvlup).min(1.0);
-            let mut rng = rand::rng();
-            let num = rng.random_range(0..3);
-            if num == 0 {
-                player.movement_speed_multiplier += CONFIG.player.movement_speed_multiplier_inc;
-            } else if num == 1 {
-                player.attack_speed_multiplier += CONFIG.player.attack_speed_multiplier_inc;
+            player.health = (player.health + config.player.health_per_lvlup).min(player.max_health);
+            let passive = &config.character(Some(&player.character)).passive;
+            player.apply_passive(passive);
+            commands
+                .entity(player_entity)
+                .with_child(ParticleEmitter::level_up());
+            commands.trigger(PlaySound(SoundEffect::LevelUp));
+
+            // every stat and every weapon that can still level up is an equally likely reward
+            let mut upgradable_weapons: Vec<Mut<Weapon>> = weapon_query
+                .iter_mut()
+                .filter(|weapon| weapon.level < config.weapon.max_level)
+                .collect();
+            let num = rng.stream(RngStream::LevelUps).random_range(0..StatUpgrade::ALL.len() + upgradable_weapons.len());
+            if let Some(upgrade) = StatUpgrade::ALL.get(num) {
+                player.apply_stat_upgrade(*upgrade, &config.player);
             } else {
-                player.xp_ball_pickup_range_multiplier +=
-                    CONFIG.player.xp_ball_pickup_range_multiplier_inc;
+                upgradable_weapons[num - StatUpgrade::ALL.len()].level += 1;
             }
         }
     }
 }
-
-#[derive(Component, Default)]
-pub struct HealthBar;
-
-#[derive(Component, Default)]
-pub struct XPBar;
-
-fn init_stat_bars(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut player_query: Query<Entity, With<Player>>,
-) {
-    if player_query.is_empty() {
-        return;
-    }
-
-    let player_entity = player_query.single_mut();
-
-    commands.entity(player_entity).with_children(|children| {
-        children.spawn((
-           HealthBar::default(),
-           Mesh2d {
-               0: meshes
-                   .add(Rectangle::new(40.0, 5.0))
-                   .into(),
-           },
-           MeshMaterial2d {
-               0: materials.add(ColorMaterial::from_color(tailwind::RED_500)),
-           },
-           Transform::from_translation(Vec3::new(0., 28.0, 101.0)),
-        ));
-
-        children.spawn((
-            Mesh2d {
-                0: meshes
-                    .add(Rectangle::new(40.0, 5.0))
-                    .into(),
-            },
-            MeshMaterial2d {
-                0: materials.add(ColorMaterial::from_color(tailwind::NEUTRAL_800)),
-            },
-            Transform::from_translation(Vec3::new(0., 28.0, 100.0)),
-        ));
-
-        children.spawn((
-            XPBar::default(),
-            Mesh2d {
-                0: meshes
-                    .add(Rectangle::new(40.0, 5.0))
-                    .into(),
-            },
-            MeshMaterial2d {
-                0: materials.add(ColorMaterial::from_color(tailwind::BLUE_600)),
-            },
-            Transform::from_translation(Vec3::new(0., 22.0, 101.0)),
-        ));
-
-        children.spawn((
-            Mesh2d {
-                0: meshes
-                    .add(Rectangle::new(40.0, 5.0))
-                    .into(),
-            },
-            MeshMaterial2d {
-                0: materials.add(ColorMaterial::from_color(tailwind::NEUTRAL_800)),
-            },
-            Transform::from_translation(Vec3::new(0., 22.0, 100.0)),
-        ));
-    });
-}
-
-fn update_stat_bars(
-    player_query: Query<&Player, With<Player>>,
-    mut health_bar_query: Query<&mut Transform, (With<HealthBar>, Without<XPBar>)>,
-    mut xp_bar_query: Query<&mut Transform, (With<XPBar>, Without<HealthBar>)>,
-) {
-    if player_query.is_empty() || health_bar_query.is_empty() || xp_bar_query.is_empty() {
-        return;
-    }
-
-    let player = player_query.single();
-
-    let player_health = player.health;
-    let mut health_bar_transform = health_bar_query.single_mut();
-    health_bar_transform.translation.x = (1.0 - player_health) * -20.0;
-    health_bar_transform.scale.x = player_health;
-
-    let player_xp_percentage = player.xp as f32 / (5 + player.level * 3) as f32;
-    let mut xp_bar_transform = xp_bar_query.single_mut();
-    xp_bar_transform.translation.x = (1.0 - player_xp_percentage) * -20.0;
-    xp_bar_transform.scale.x = player_xp_percentage;
-}
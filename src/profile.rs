@@ -0,0 +1,169 @@
+use crate::config::{CharacterConfig, Config, ShopConfig, ShopItemConfig, UnlockCondition};
+use crate::game_over::RunStats;
+use crate::player::Player;
+use crate::settings::{read_user_toml, write_user_toml};
+use crate::state::GameState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+const PROFILE_FILE: &str = "profile.toml";
+
+/// Permanent upgrades bought in the shop, applied to every run from its start.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermanentUpgrade {
+    StartingHealth,
+    Might,
+    Speed,
+    Magnet,
+    Revival,
+}
+
+impl PermanentUpgrade {
+    pub const ALL: [PermanentUpgrade; 5] = [
+        PermanentUpgrade::StartingHealth,
+        PermanentUpgrade::Might,
+        PermanentUpgrade::Speed,
+        PermanentUpgrade::Magnet,
+        PermanentUpgrade::Revival,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PermanentUpgrade::StartingHealth => "Starting Health",
+            PermanentUpgrade::Might => "Might",
+            PermanentUpgrade::Speed => "Speed",
+            PermanentUpgrade::Magnet => "Magnet",
+            PermanentUpgrade::Revival => "Revival",
+        }
+    }
+
+    pub fn item(self, config: &ShopConfig) -> &ShopItemConfig {
+        match self {
+            PermanentUpgrade::StartingHealth => &config.starting_health,
+            PermanentUpgrade::Might => &config.might,
+            PermanentUpgrade::Speed => &config.speed,
+            PermanentUpgrade::Magnet => &config.magnet,
+            PermanentUpgrade::Revival => &config.revival,
+        }
+    }
+}
+
+/// What carries over between runs: banked gold, the permanent upgrades bought with it and the
+/// characters unlocked so far.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub gold: u32,
+    pub upgrades: HashMap<PermanentUpgrade, u32>,
+    pub unlocked_characters: BTreeSet<String>,
+}
+
+impl Profile {
+    pub fn load() -> Self {
+        read_user_toml(PROFILE_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        write_user_toml(PROFILE_FILE, self);
+    }
+
+    pub fn is_unlocked(&self, character: &CharacterConfig) -> bool {
+        character.unlock.is_none() || self.unlocked_characters.contains(&character.name)
+    }
+
+    /// Levels bought, never more than the config allows even if it has been lowered since.
+    pub fn level(&self, upgrade: PermanentUpgrade, config: &ShopConfig) -> u32 {
+        let level = self.upgrades.get(&upgrade).copied().unwrap_or_default();
+        level.min(upgrade.item(config).max_level)
+    }
+
+    /// How much the upgrade adds to a run, on top of the base stat of 1 or no revives.
+    pub fn bonus(&self, upgrade: PermanentUpgrade, config: &ShopConfig) -> f32 {
+        self.level(upgrade, config) as f32 * upgrade.item(config).bonus_per_level
+    }
+
+    /// The price of the next level, or `None` once it is maxed out.
+    pub fn cost(&self, upgrade: PermanentUpgrade, config: &ShopConfig) -> Option<u32> {
+        let item = upgrade.item(config);
+        let level = self.level(upgrade, config);
+        (level < item.max_level)
+            .then(|| (item.base_cost as f32 * item.cost_growth.powi(level as i32)).round() as u32)
+    }
+
+    /// Buys the next level if there is one and it can be afforded.
+    pub fn buy(&mut self, upgrade: PermanentUpgrade, config: &ShopConfig) -> bool {
+        let Some(cost) = self.cost(upgrade, config).filter(|&cost| cost <= self.gold) else {
+            return false;
+        };
+        self.gold -= cost;
+        *self.upgrades.entry(upgrade).or_default() = self.level(upgrade, config) + 1;
+        true
+    }
+}
+
+/// Banks the gold from every run that ends, unlocks characters as soon as a run earns them and
+/// saves the profile whenever it changes; left out when nobody is playing to keep it.
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Dying), bank_run_gold)
+            .add_systems(
+                Update,
+                unlock_characters.run_if(in_state(GameState::Gaming)),
+            )
+            .add_systems(
+                Last,
+                save_profile
+                    .run_if(resource_changed::<Profile>.and(not(resource_added::<Profile>))),
+            );
+    }
+}
+
+fn bank_run_gold(run_stats: Res<RunStats>, mut profile: ResMut<Profile>) {
+    if run_stats.gold == 0 {
+        return;
+    }
+    profile.gold += run_stats.gold;
+}
+
+fn save_profile(profile: Res<Profile>) {
+    profile.save();
+}
+
+fn unlock_characters(
+    config: Res<Config>,
+    run_stats: Res<RunStats>,
+    player_query: Query<&Player, With<Player>>,
+    mut profile: ResMut<Profile>,
+) {
+    if player_query.is_empty() {
+        return;
+    }
+
+    let player = player_query.single();
+    for character in config.characters.iter() {
+        let Some(condition) = character.unlock else {
+            continue;
+        };
+        if profile.unlocked_characters.contains(&character.name)
+            || !is_met(condition, &run_stats, player)
+        {
+            continue;
+        }
+        info!("Unlocked {}", character.name);
+        profile.unlocked_characters.insert(character.name.clone());
+    }
+}
+
+fn is_met(condition: UnlockCondition, run_stats: &RunStats, player: &Player) -> bool {
+    match condition {
+        UnlockCondition::SurviveMinutes(minutes) => {
+            run_stats.time_survived.elapsed_secs() >= minutes as f32 * 60.0
+        }
+        UnlockCondition::ReachLevel(level) => player.level >= level,
+        UnlockCondition::KillEnemies(kills) => run_stats.kills >= kills,
+    }
+}
@@ -0,0 +1,269 @@
+use crate::config::{Config, Difficulty};
+use crate::enemy::SpawnView;
+use crate::input::{Action, ActionInput};
+use crate::player::ChosenCharacter;
+use crate::profile::{PermanentUpgrade, Profile};
+use crate::rng::{seed_run, GameRng};
+use crate::save::ResumeRun;
+use crate::settings::{load_versioned, write_atomically};
+use crate::state::{GameState, InRun};
+use crate::weapon::Aim;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Bumped whenever `Recording` changes shape, so older replays are turned away instead of misread.
+const RECORDING_VERSION: u32 = 2;
+
+/// What the player did during one frame of a run, how long that frame took and what the camera
+/// showed, which decides where enemies may spawn.
+///
+/// Only gameplay input is kept, so pausing and menus never make it into a replay.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct InputFrame {
+    delta: f64,
+    movement: (f32, f32),
+    dash: bool,
+    aim: (f32, f32),
+    firing: bool,
+    view: [f32; 4],
+}
+
+impl InputFrame {
+    pub fn movement(&self) -> Vec2 {
+        self.movement.into()
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        action == Action::Fire && self.firing
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        action == Action::Dash && self.dash
+    }
+
+    fn view(&self) -> Rect {
+        let [min_x, min_y, max_x, max_y] = self.view;
+        Rect::new(min_x, min_y, max_x, max_y)
+    }
+}
+
+/// A run's seed, difficulty, character and permanent upgrades with every frame of input, enough
+/// to play it out again.
+#[derive(Serialize, Deserialize)]
+pub struct Recording {
+    version: u32,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub character: String,
+    pub upgrades: HashMap<PermanentUpgrade, u32>,
+    frames: Vec<InputFrame>,
+}
+
+impl Recording {
+    pub fn load(path: &str) -> Result<Self, String> {
+        load_versioned(Path::new(path), RECORDING_VERSION)
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        let recording_str = toml::to_string(self).map_err(|err| err.to_string())?;
+        write_atomically(Path::new(path), &recording_str).map_err(|err| err.to_string())
+    }
+}
+
+/// Records the first run after launch to `path`.
+#[derive(Resource)]
+pub struct Recorder {
+    path: String,
+    recording: Option<Recording>,
+}
+
+impl Recorder {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            recording: None,
+        }
+    }
+}
+
+/// Plays a recording back in place of the player's input, starting with the first run.
+#[derive(Resource)]
+pub struct Replay {
+    recording: Recording,
+    frame: Option<usize>,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            frame: None,
+        }
+    }
+
+    /// This frame's recorded input, while the replayed run is in progress.
+    pub fn frame(&self) -> Option<&InputFrame> {
+        self.frame
+            .and_then(|frame| self.recording.frames.get(frame))
+    }
+}
+
+/// Triggered once a replay has played out and control goes back to the player.
+#[derive(Event)]
+pub struct ReplayFinished;
+
+/// Run condition for systems that would fight a replay over what the player is doing.
+pub fn replaying(replay: Option<Res<Replay>>) -> bool {
+    replay.is_some_and(|replay| replay.frame.is_some())
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::Initializing),
+            (
+                // a resumed run could not be replayed from its start
+                start_recording
+                    .after(seed_run)
+                    .run_if(resource_exists::<Recorder>.and(not(resource_exists::<ResumeRun>))),
+                start_replay.run_if(resource_exists::<Replay>),
+            ),
+        )
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            save_recording.run_if(resource_exists::<Recorder>),
+        )
+        .add_systems(
+            OnExit(InRun),
+            save_recording.run_if(resource_exists::<Recorder>),
+        )
+        .add_systems(
+            Last,
+            (
+                record_frame.run_if(resource_exists::<Recorder>.and(in_recorded_state)),
+                save_recording.run_if(resource_exists::<Recorder>.and(on_event::<AppExit>)),
+                advance_replay.run_if(resource_exists::<Replay>),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Runs are recorded from the frame they start until the player dies, leaving out pauses.
+fn in_recorded_state(state: Res<State<GameState>>) -> bool {
+    matches!(state.get(), GameState::Initializing | GameState::Gaming)
+}
+
+fn start_recording(
+    mut recorder: ResMut<Recorder>,
+    config: Res<Config>,
+    profile: Res<Profile>,
+    chosen_character: Res<ChosenCharacter>,
+    rng: Res<GameRng>,
+) {
+    recorder.recording = Some(Recording {
+        version: RECORDING_VERSION,
+        seed: rng.seed(),
+        difficulty: config.game.difficulty,
+        character: config.character(chosen_character.0.as_deref()).name.clone(),
+        upgrades: profile.upgrades.clone(),
+        frames: Vec::new(),
+    });
+}
+
+fn record_frame(
+    mut recorder: ResMut<Recorder>,
+    time: Res<Time<Real>>,
+    actions: ActionInput,
+    aim: Res<Aim>,
+    spawn_view: Res<SpawnView>,
+) {
+    let Some(recording) = recorder.recording.as_mut() else {
+        return;
+    };
+    recording.frames.push(InputFrame {
+        delta: time.delta().as_secs_f64(),
+        movement: actions.movement().into(),
+        dash: actions.just_pressed(Action::Dash),
+        aim: aim.direction.into(),
+        firing: aim.firing,
+        view: [
+            spawn_view.min.x,
+            spawn_view.min.y,
+            spawn_view.max.x,
+            spawn_view.max.y,
+        ],
+    });
+}
+
+fn save_recording(mut commands: Commands, mut recorder: ResMut<Recorder>) {
+    let Some(recording) = recorder.recording.take() else {
+        return;
+    };
+    match recording.save(&recorder.path) {
+        Ok(()) => info!(
+            "Recorded {} frames to {}",
+            recording.frames.len(),
+            recorder.path
+        ),
+        Err(err) => warn!("Cannot save recording {}: {err}", recorder.path),
+    }
+    commands.remove_resource::<Recorder>();
+}
+
+fn start_replay(mut replay: ResMut<Replay>, mut spawn_view: ResMut<SpawnView>) {
+    info!(
+        "Replaying {} frames with seed {}",
+        replay.recording.frames.len(),
+        replay.recording.seed
+    );
+    if let Some(first_frame) = replay.recording.frames.first() {
+        spawn_view.0 = first_frame.view();
+    }
+    replay.frame = Some(0);
+}
+
+/// Moves on to the next recorded frame, giving it the same length of time it had when recorded.
+fn advance_replay(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    mut replay: ResMut<Replay>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut aim: ResMut<Aim>,
+    mut spawn_view: ResMut<SpawnView>,
+) {
+    let Some(frame) = replay.frame else {
+        return;
+    };
+
+    let next = frame + 1;
+    let remaining = replay.recording.frames.len().saturating_sub(next);
+    let run_going = in_recorded_state(state);
+    match replay.recording.frames.get(next).copied() {
+        Some(next_frame) if run_going => {
+            *time_strategy =
+                TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(next_frame.delta));
+            aim.direction = next_frame.aim.into();
+            aim.firing = next_frame.firing;
+            spawn_view.0 = next_frame.view();
+            replay.frame = Some(next);
+        }
+        _ => {
+            // a replay only plays out the same on the build and config it was recorded with
+            if remaining > 0 {
+                warn!("Replayed run ended {remaining} frames early");
+            } else {
+                info!("Replay finished");
+            }
+            *time_strategy = TimeUpdateStrategy::Automatic;
+            commands.remove_resource::<Replay>();
+            commands.trigger(ReplayFinished);
+        }
+    }
+}
@@ -1,6 +1,10 @@
-use crate::config::CONFIG;
-use crate::state::GameState;
+use crate::animation::{AnimationController, AnimationState, SpriteAnimation};
+use crate::audio::AudioAssets;
+use crate::config::Config;
+use crate::sprites::{AnimationClip, SpriteManifest};
+use crate::state::{GameState, StartState};
 use bevy::app::{App, Plugin};
+use bevy::asset::{RecursiveDependencyLoadState, UntypedAssetId};
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 
@@ -8,47 +12,236 @@ pub struct ResourcesPlugin;
 
 impl Plugin for ResourcesPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(ClearColor(Color::srgb_u8(
-            CONFIG.game.background_color.0,
-            CONFIG.game.background_color.1,
-            CONFIG.game.background_color.2,
-        )))
-        .insert_resource(GlobalTextureAtlas::default())
-        .insert_resource(CursorPosition(None))
-        .add_systems(OnEnter(GameState::Loading), load_assets)
-        .add_systems(
-            Update,
-            update_cursor_position.run_if(in_state(GameState::Gaming)),
-        );
+        app.insert_resource(CursorPosition(None))
+            .init_resource::<StartState>()
+            .add_systems(
+                Update,
+                update_clear_color.run_if(resource_changed::<Config>),
+            )
+            .add_systems(
+                OnEnter(GameState::Loading),
+                (load_assets, spawn_loading_screen),
+            )
+            .add_systems(
+                Update,
+                (
+                    check_assets_loaded,
+                    leave_loading_screen
+                        .after(check_assets_loaded)
+                        .run_if(resource_added::<GlobalTextureAtlas>),
+                )
+                    .run_if(in_state(GameState::Loading)),
+            )
+            .add_systems(
+                Update,
+                update_cursor_position.run_if(in_state(GameState::Gaming)),
+            );
     }
 }
 
-#[derive(Resource, Default)]
+/// The spritesheet and its manifest, available once the loading screen has finished.
+#[derive(Resource)]
 pub struct GlobalTextureAtlas {
-    pub(crate) layout: Option<Handle<TextureAtlasLayout>>,
-    pub(crate) image: Option<Handle<Image>>,
+    pub(crate) layout: Handle<TextureAtlasLayout>,
+    pub(crate) image: Handle<Image>,
+    pub(crate) manifest: SpriteManifest,
+}
+
+impl GlobalTextureAtlas {
+    pub fn sprite(&self, name: &str) -> Sprite {
+        self.sprite_variant(name, 0)
+    }
+
+    /// One of the interchangeable cells of a sprite, wrapping around past the last one.
+    pub fn sprite_variant(&self, name: &str, variant: u32) -> Sprite {
+        self.atlas_sprite(self.index(name, variant))
+    }
+
+    pub fn variants(&self, name: &str) -> u32 {
+        self.manifest
+            .sprites
+            .get(name)
+            .map_or(1, |cell| cell.variants)
+    }
+
+    /// Atlas index of a named sprite; the manifest is validated against the names the game uses.
+    pub fn index(&self, name: &str, variant: u32) -> usize {
+        self.manifest.sprites.get(name).map_or(0, |cell| {
+            self.manifest
+                .index(cell.row, cell.column + variant % cell.variants)
+        })
+    }
+
+    pub fn animation(&self, name: &str) -> SpriteAnimation {
+        self.manifest.animations.get(name).map_or_else(
+            || SpriteAnimation::new(0, &AnimationClip::default()),
+            |clip| SpriteAnimation::new(self.manifest.index(clip.row, clip.column), clip),
+        )
+    }
+
+    /// A sprite driven by the `<character>_idle`, `_run`, `_hurt` and `_die` clips.
+    pub fn animated_sprite(
+        &self,
+        character: &str,
+    ) -> (Sprite, SpriteAnimation, AnimationController) {
+        let controller = AnimationController::new(
+            AnimationState::ALL
+                .map(|state| self.animation(&format!("{character}_{}", state.clip_suffix()))),
+        );
+        let animation = controller.clip(controller.state()).clone();
+        (self.atlas_sprite(animation.index()), animation, controller)
+    }
+
+    /// Any cell of the spritesheet by its atlas index.
+    pub fn atlas_sprite(&self, index: usize) -> Sprite {
+        Sprite {
+            image: self.image.clone(),
+            texture_atlas: Some(TextureAtlas {
+                layout: self.layout.clone(),
+                index,
+            }),
+            ..default()
+        }
+    }
+}
+
+/// Every asset that has to finish loading before the game can leave the loading screen.
+#[derive(Resource)]
+pub struct LoadingAssets {
+    pub sprite_manifest: Handle<SpriteManifest>,
+    pub audio: Vec<UntypedAssetId>,
+}
+
+impl LoadingAssets {
+    fn handles(&self) -> Vec<UntypedAssetId> {
+        let mut handles = vec![self.sprite_manifest.id().untyped()];
+        handles.extend_from_slice(&self.audio);
+        handles
+    }
 }
 
 #[derive(Resource, Default)]
 pub struct CursorPosition(pub(crate) Option<Vec2>);
 
-fn load_assets(
-    mut texture_handle: ResMut<GlobalTextureAtlas>,
+#[derive(Component)]
+struct LoadingStatus;
+
+fn update_clear_color(mut commands: Commands, config: Res<Config>) {
+    commands.insert_resource(ClearColor(config.game.background_color()));
+}
+
+fn load_assets(mut commands: Commands, asset_server: Res<AssetServer>, config: Res<Config>) {
+    let manifest: Handle<SpriteManifest> = asset_server.load(config.sprite.manifest_path.clone());
+    let audio_assets = AudioAssets::load(&asset_server);
+    commands.insert_resource(LoadingAssets {
+        sprite_manifest: manifest,
+        audio: audio_assets.handles().collect(),
+    });
+    commands.insert_resource(audio_assets);
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            StateScoped(GameState::Loading),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+        ))
+        .with_children(|root| {
+            root.spawn((LoadingStatus, Text::new("Loading...")));
+        });
+}
+
+fn check_assets_loaded(
+    mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    loading_assets: Res<LoadingAssets>,
+    config: Res<Config>,
+    manifests: Res<Assets<SpriteManifest>>,
+    images: Res<Assets<Image>>,
+    mut status_query: Query<&mut Text, With<LoadingStatus>>,
+) {
+    let mut failures = Vec::new();
+    let mut loaded = 0;
+    let handles = loading_assets.handles();
+    for handle in handles.iter().copied() {
+        match asset_server.recursive_dependency_load_state(handle) {
+            RecursiveDependencyLoadState::Loaded => loaded += 1,
+            RecursiveDependencyLoadState::Failed(err) => failures.push(err.to_string()),
+            RecursiveDependencyLoadState::NotLoaded | RecursiveDependencyLoadState::Loading => {}
+        }
+    }
+
+    if failures.is_empty() && loaded == handles.len() {
+        match build_texture_atlas(
+            &loading_assets.sprite_manifest,
+            &config,
+            &manifests,
+            &images,
+        ) {
+            Ok(texture_atlas) => {
+                commands.insert_resource(texture_atlas);
+                return;
+            }
+            Err(err) => failures.push(match loading_assets.sprite_manifest.path() {
+                Some(path) => format!("{path}: {err}"),
+                None => err,
+            }),
+        }
+    }
+
+    let message = if failures.is_empty() {
+        format!("Loading... {loaded}/{}", handles.len())
+    } else {
+        format!("Failed to load assets:\n{}", failures.join("\n"))
+    };
+    for mut text in status_query.iter_mut() {
+        if text.0 != message {
+            if !failures.is_empty() {
+                error!("{message}");
+            }
+            text.0 = message.clone();
+        }
+    }
+}
+
+fn leave_loading_screen(
+    start_state: Res<StartState>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    texture_handle.image = Some(asset_server.load(CONFIG.sprite.spritesheet_path.clone()));
-    let layout = TextureAtlasLayout::from_grid(
-        UVec2::new(CONFIG.sprite.sprite_height, CONFIG.sprite.sprite_width),
-        CONFIG.sprite.spritesheet_width,
-        CONFIG.sprite.spritesheet_height,
-        None,
-        None,
-    );
-    texture_handle.layout = Some(texture_atlas_layouts.add(layout));
-
-    next_state.set(GameState::Initializing);
+    next_state.set(start_state.0);
+}
+
+fn build_texture_atlas(
+    manifest_handle: &Handle<SpriteManifest>,
+    config: &Config,
+    manifests: &Assets<SpriteManifest>,
+    images: &Assets<Image>,
+) -> Result<GlobalTextureAtlas, String> {
+    let manifest = manifests
+        .get(manifest_handle)
+        .ok_or("manifest is not loaded")?;
+    let image = images
+        .get(&manifest.image_handle)
+        .ok_or("spritesheet is not loaded")?;
+    manifest.validate(
+        image.size(),
+        config
+            .characters
+            .iter()
+            .map(|character| character.sprite.as_str()),
+    )?;
+
+    Ok(GlobalTextureAtlas {
+        layout: manifest.layout_handle.clone(),
+        image: manifest.image_handle.clone(),
+        manifest: manifest.clone(),
+    })
 }
 
 fn update_cursor_position(
@@ -58,6 +251,7 @@ fn update_cursor_position(
 ) {
     if window_query.is_empty() || camera_query.is_empty() {
         cursor_position.0 = None;
+        return;
     }
 
     let (camera, camera_transform) = camera_query.single();
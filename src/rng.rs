@@ -0,0 +1,130 @@
+use crate::state::GameState;
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+/// The independent sources of randomness in a run.
+///
+/// Each has its own generator, so systems running in either order still draw the same numbers.
+#[derive(Clone, Copy)]
+pub enum RngStream {
+    World,
+    EnemySpawns,
+    LevelUps,
+    ProjectileCrits,
+    LightningCrits,
+    Loot,
+}
+
+impl RngStream {
+    const COUNT: usize = 6;
+}
+
+/// Seeds are kept to what a TOML integer can hold, so recordings and saves can store them.
+pub const MAX_SEED: u64 = i64::MAX as u64;
+
+/// Randomness that shapes a run, reseeded as each one starts so a seed plays out the same way.
+///
+/// Purely cosmetic randomness like particles and screen shake doesn't go through here. The
+/// streams use the generator behind `StdRng` directly, as it can tell how far along it is.
+#[derive(Resource)]
+pub struct GameRng {
+    fixed_seed: Option<u64>,
+    resumed_seed: Option<u64>,
+    seed: u64,
+    streams: Vec<ChaCha12Rng>,
+}
+
+/// How far along every stream of a run is.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RngState {
+    seed: u64,
+    word_positions: Vec<u64>,
+}
+
+impl GameRng {
+    /// Every run uses `fixed_seed` when given, or a fresh random seed otherwise.
+    pub fn new(fixed_seed: Option<u64>) -> Self {
+        let mut rng = Self {
+            fixed_seed,
+            resumed_seed: None,
+            seed: 0,
+            streams: Vec::new(),
+        };
+        rng.reseed();
+        rng
+    }
+
+    /// The seed of the current run.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha12Rng {
+        &mut self.streams[stream as usize]
+    }
+
+    /// Seeds only the next run like the saved one, so it builds the same world.
+    pub fn resume_seed(&mut self, state: &RngState) {
+        self.resumed_seed = Some(state.seed);
+    }
+
+    pub fn state(&self) -> RngState {
+        RngState {
+            seed: self.seed,
+            // a run would have to draw for centuries to get past what a TOML integer holds
+            word_positions: self
+                .streams
+                .iter()
+                .map(|stream| stream.get_word_pos() as u64)
+                .collect(),
+        }
+    }
+
+    /// Carries every stream on from where `state` left it.
+    pub fn restore(&mut self, state: &RngState) {
+        self.seed_streams(state.seed);
+        for (stream, &word_position) in self.streams.iter_mut().zip(&state.word_positions) {
+            stream.set_word_pos(word_position.into());
+        }
+    }
+
+    fn reseed(&mut self) {
+        let seed = self
+            .resumed_seed
+            .take()
+            .or(self.fixed_seed)
+            .unwrap_or_else(|| rand::random_range(0..=MAX_SEED));
+        self.seed_streams(seed);
+    }
+
+    fn seed_streams(&mut self, seed: u64) {
+        self.seed = seed;
+        let mut seeds = ChaCha12Rng::seed_from_u64(seed);
+        self.streams = (0..RngStream::COUNT)
+            .map(|_| ChaCha12Rng::seed_from_u64(seeds.random()))
+            .collect();
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>()
+            .add_systems(OnEnter(GameState::Initializing), seed_run);
+    }
+}
+
+/// Anything random spawned as a run starts has to be ordered after this.
+pub fn seed_run(mut rng: ResMut<GameRng>) {
+    rng.reseed();
+    info!("Run seed {}", rng.seed);
+}
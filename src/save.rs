@@ -0,0 +1,417 @@
+use crate::config::Config;
+use crate::enemy::{Enemy, EnemyKind, EnemySpawnTimer};
+use crate::game_over::RunStats;
+use crate::gold::GoldCoin;
+use crate::player::{Player, StatUpgrade};
+use crate::profile::Profile;
+use crate::resources::GlobalTextureAtlas;
+use crate::rng::{seed_run, GameRng, RngState};
+use crate::settings::{load_versioned, user_file, write_atomically};
+use crate::state::GameState;
+use crate::weapon::{
+    spawn_projectile, HeldWeapon, Homing, Projectile, ProjectileDirection, Weapon, WeaponKind,
+    WeaponTimer,
+};
+use crate::xp_ball::XPBall;
+use bevy::ecs::system::SystemParam;
+use bevy::math::vec3;
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const SAVE_FILE: &str = "run.toml";
+/// Bumped whenever `SavedRun` changes shape, so older saves are turned away instead of misread.
+const SAVE_VERSION: u32 = 4;
+
+/// Everything needed to carry on with a run after quitting.
+///
+/// Short-lived effects like lightning arcs, particles and dying enemies are left out, and the
+/// decorations are rebuilt from the seed.
+#[derive(Serialize, Deserialize)]
+pub struct SavedRun {
+    version: u32,
+    rng: RngState,
+    time_survived: f32,
+    kills: u32,
+    gold: u32,
+    kills_by_enemy: HashMap<EnemyKind, u32>,
+    damage_by_weapon: HashMap<WeaponKind, f32>,
+    xp_collected: u32,
+    last_hit_by: Option<EnemyKind>,
+    since_last_wave: f32,
+    player: SavedPlayer,
+    weapons: Vec<SavedWeapon>,
+    enemies: Vec<SavedEnemy>,
+    xp_balls: Vec<(f32, f32)>,
+    gold_coins: Vec<(f32, f32)>,
+    projectiles: Vec<SavedProjectile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPlayer {
+    character: String,
+    position: (f32, f32),
+    xp: u32,
+    level: u32,
+    health: f32,
+    max_health: f32,
+    damage_multiplier: f32,
+    revivals: u32,
+    attack_speed_multiplier: f32,
+    movement_speed_multiplier: f32,
+    xp_ball_pickup_range_multiplier: f32,
+    stat_upgrades: HashMap<StatUpgrade, u32>,
+    since_last_dash: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedWeapon {
+    kind: WeaponKind,
+    level: u32,
+    held: bool,
+    since_last_shot: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEnemy {
+    kind: EnemyKind,
+    position: (f32, f32),
+    health: f32,
+    since_last_attack: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedProjectile {
+    weapon: WeaponKind,
+    position: (f32, f32),
+    direction: (f32, f32),
+    damage: f32,
+    speed: f32,
+    homing: bool,
+}
+
+/// Where the run in progress is saved, only there when saving is enabled.
+#[derive(Resource)]
+pub struct SaveSlot {
+    path: PathBuf,
+}
+
+impl Default for SaveSlot {
+    fn default() -> Self {
+        Self {
+            path: user_file(SAVE_FILE),
+        }
+    }
+}
+
+impl SaveSlot {
+    /// The saved run, or `None` if there isn't one.
+    pub fn load(&self) -> Result<Option<SavedRun>, String> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        load_versioned(&self.path, SAVE_VERSION).map(Some)
+    }
+
+    fn save(&self, run: &SavedRun) {
+        let result = toml::to_string(run)
+            .map_err(|err| err.to_string())
+            .and_then(|save_str| {
+                write_atomically(&self.path, &save_str).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            warn!("Cannot save the run to {}: {err}", self.path.display());
+        }
+    }
+
+    fn delete(&self) {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                warn!("Cannot delete {}: {err}", self.path.display())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The saved run to pick up from as the next run starts, in place of a fresh one.
+#[derive(Resource)]
+pub struct ResumeRun(pub SavedRun);
+
+/// Everything spawned for the run that is worth saving.
+#[derive(SystemParam)]
+struct RunEntities<'w, 's> {
+    players: Query<'w, 's, (&'static Transform, &'static Player)>,
+    weapons: Query<'w, 's, (&'static Weapon, &'static WeaponTimer, Has<HeldWeapon>)>,
+    enemies: Query<'w, 's, (&'static Transform, &'static Enemy)>,
+    xp_balls: Query<'w, 's, &'static Transform, With<XPBall>>,
+    gold_coins: Query<'w, 's, &'static Transform, With<GoldCoin>>,
+    projectiles: Query<
+        'w,
+        's,
+        (
+            &'static Transform,
+            &'static Projectile,
+            &'static ProjectileDirection,
+            Has<Homing>,
+        ),
+    >,
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSlot>()
+            .add_systems(
+                OnEnter(GameState::Initializing),
+                (
+                    resume_seed
+                        .before(seed_run)
+                        .run_if(resource_exists::<ResumeRun>),
+                    // starting over gives up the saved run
+                    delete_save.run_if(not(resource_exists::<ResumeRun>)),
+                ),
+            )
+            .add_systems(
+                Update,
+                (restore_progress, respawn_saved_run)
+                    .run_if(in_state(GameState::Initializing))
+                    .run_if(resource_exists::<ResumeRun>),
+            )
+            .add_systems(OnExit(GameState::Initializing), finish_resuming)
+            .add_systems(OnEnter(GameState::Paused), save_run)
+            .add_systems(OnEnter(GameState::Dying), delete_save)
+            .add_systems(
+                Last,
+                save_run.run_if(on_event::<AppExit>.and(in_saveable_state)),
+            );
+    }
+}
+
+/// The run is still going, while dying and game over leave nothing to come back to.
+fn in_saveable_state(state: Res<State<GameState>>) -> bool {
+    matches!(
+        state.get(),
+        GameState::Gaming | GameState::Paused | GameState::Settings { in_run: true }
+    )
+}
+
+fn save_run(
+    slot: Res<SaveSlot>,
+    rng: Res<GameRng>,
+    run_stats: Res<RunStats>,
+    spawn_timer: Res<EnemySpawnTimer>,
+    run: RunEntities,
+) {
+    // settings opened from the main menu are not part of any run
+    let Ok((player_transform, player)) = run.players.get_single() else {
+        return;
+    };
+
+    slot.save(&SavedRun {
+        version: SAVE_VERSION,
+        rng: rng.state(),
+        time_survived: run_stats.time_survived.elapsed_secs(),
+        kills: run_stats.kills,
+        gold: run_stats.gold,
+        kills_by_enemy: run_stats.kills_by_enemy.clone(),
+        damage_by_weapon: run_stats.damage_by_weapon.clone(),
+        xp_collected: run_stats.xp_collected,
+        last_hit_by: run_stats.last_hit_by,
+        since_last_wave: spawn_timer.elapsed_secs(),
+        player: SavedPlayer {
+            character: player.character.clone(),
+            position: player_transform.translation.truncate().into(),
+            xp: player.xp,
+            level: player.level,
+            health: player.health,
+            max_health: player.max_health,
+            damage_multiplier: player.damage_multiplier,
+            revivals: player.revivals,
+            attack_speed_multiplier: player.attack_speed_multiplier,
+            movement_speed_multiplier: player.movement_speed_multiplier,
+            xp_ball_pickup_range_multiplier: player.xp_ball_pickup_range_multiplier,
+            stat_upgrades: player.stat_upgrades.clone(),
+            since_last_dash: player.dash_timer.elapsed_secs(),
+        },
+        weapons: run
+            .weapons
+            .iter()
+            .map(|(weapon, weapon_timer, held)| SavedWeapon {
+                kind: weapon.kind,
+                level: weapon.level,
+                held,
+                since_last_shot: weapon_timer.0.elapsed_secs(),
+            })
+            .collect(),
+        enemies: run
+            .enemies
+            .iter()
+            .map(|(transform, enemy)| SavedEnemy {
+                kind: enemy.kind,
+                position: transform.translation.truncate().into(),
+                health: enemy.health,
+                since_last_attack: enemy.attack_timer.elapsed_secs(),
+            })
+            .collect(),
+        xp_balls: run
+            .xp_balls
+            .iter()
+            .map(|transform| transform.translation.truncate().into())
+            .collect(),
+        gold_coins: run
+            .gold_coins
+            .iter()
+            .map(|transform| transform.translation.truncate().into())
+            .collect(),
+        projectiles: run
+            .projectiles
+            .iter()
+            .map(
+                |(transform, projectile, direction, homing)| SavedProjectile {
+                    weapon: projectile.weapon,
+                    position: transform.translation.truncate().into(),
+                    direction: direction.0.truncate().into(),
+                    damage: projectile.damage,
+                    speed: projectile.speed,
+                    homing,
+                },
+            )
+            .collect(),
+    });
+}
+
+fn delete_save(slot: Res<SaveSlot>) {
+    slot.delete();
+}
+
+fn resume_seed(resume: Res<ResumeRun>, mut rng: ResMut<GameRng>) {
+    rng.resume_seed(&resume.0.rng);
+}
+
+fn restore_progress(
+    resume: Res<ResumeRun>,
+    mut rng: ResMut<GameRng>,
+    mut run_stats: ResMut<RunStats>,
+    mut spawn_timer: ResMut<EnemySpawnTimer>,
+) {
+    let run = &resume.0;
+    rng.restore(&run.rng);
+    run_stats
+        .time_survived
+        .set_elapsed(Duration::from_secs_f32(run.time_survived));
+    run_stats.kills = run.kills;
+    run_stats.gold = run.gold;
+    run_stats.kills_by_enemy = run.kills_by_enemy.clone();
+    run_stats.damage_by_weapon = run.damage_by_weapon.clone();
+    run_stats.xp_collected = run.xp_collected;
+    run_stats.last_hit_by = run.last_hit_by;
+    spawn_timer.set_elapsed(Duration::from_secs_f32(run.since_last_wave));
+    info!("Resumed run at {}", run_stats.time_survived_label());
+}
+
+fn respawn_saved_run(
+    mut commands: Commands,
+    config: Res<Config>,
+    texture_handle: Res<GlobalTextureAtlas>,
+    resume: Res<ResumeRun>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let run = &resume.0;
+    let scale = Vec3::splat(config.sprite.sprite_scale_factor);
+
+    let saved_player = &run.player;
+    let character = config.character(Some(&saved_player.character));
+    // upgrades bought since the save don't apply to a run already under way
+    let mut player = Player::new(&config, &Profile::default(), character);
+    player.xp = saved_player.xp;
+    player.level = saved_player.level;
+    player.health = saved_player.health;
+    player.max_health = saved_player.max_health;
+    player.damage_multiplier = saved_player.damage_multiplier;
+    player.revivals = saved_player.revivals;
+    player.attack_speed_multiplier = saved_player.attack_speed_multiplier;
+    player.movement_speed_multiplier = saved_player.movement_speed_multiplier;
+    player.xp_ball_pickup_range_multiplier = saved_player.xp_ball_pickup_range_multiplier;
+    player.stat_upgrades = saved_player.stat_upgrades.clone();
+    player
+        .dash_timer
+        .set_elapsed(Duration::from_secs_f32(saved_player.since_last_dash));
+    commands.spawn((
+        player,
+        texture_handle.animated_sprite(&character.sprite),
+        Transform::from_translation(vec3(saved_player.position.0, saved_player.position.1, 10.0))
+            .with_scale(scale),
+    ));
+
+    for saved_weapon in run.weapons.iter() {
+        let weapon = Weapon {
+            kind: saved_weapon.kind,
+            level: saved_weapon.level,
+        };
+        let mut weapon_timer = Stopwatch::new();
+        weapon_timer.set_elapsed(Duration::from_secs_f32(saved_weapon.since_last_shot));
+        if saved_weapon.held {
+            commands.spawn((
+                weapon,
+                HeldWeapon,
+                texture_handle.sprite(saved_weapon.kind.sprite_name()),
+                Transform::from_scale(scale),
+                WeaponTimer(weapon_timer),
+            ));
+        } else {
+            commands.spawn((weapon, WeaponTimer(weapon_timer)));
+        }
+    }
+
+    for saved_enemy in run.enemies.iter() {
+        let mut enemy = Enemy::new(saved_enemy.kind, saved_enemy.health);
+        enemy
+            .attack_timer
+            .set_elapsed(Duration::from_secs_f32(saved_enemy.since_last_attack));
+        commands.spawn((
+            enemy,
+            texture_handle.animated_sprite("enemy"),
+            Transform::from_translation(vec3(saved_enemy.position.0, saved_enemy.position.1, 1.0))
+                .with_scale(scale),
+        ));
+    }
+
+    for &(x, y) in run.xp_balls.iter() {
+        XPBall::spawn(&mut commands, vec3(x, y, 1.0), &texture_handle, &config);
+    }
+    for &(x, y) in run.gold_coins.iter() {
+        GoldCoin::spawn(&mut commands, vec3(x, y, 1.0), &texture_handle, &config);
+    }
+
+    for saved_projectile in run.projectiles.iter() {
+        let mut projectile = spawn_projectile(
+            &mut commands,
+            &config,
+            saved_projectile.position.into(),
+            saved_projectile.direction.into(),
+            Projectile {
+                weapon: saved_projectile.weapon,
+                damage: saved_projectile.damage,
+                speed: saved_projectile.speed,
+            },
+            texture_handle.sprite(saved_projectile.weapon.projectile_sprite_name()),
+        );
+        // missiles pick the nearest enemy again on their next frame
+        if saved_projectile.homing {
+            projectile.insert(Homing { target: None });
+        }
+    }
+
+    next_state.set(GameState::Gaming);
+}
+
+fn finish_resuming(mut commands: Commands) {
+    commands.remove_resource::<ResumeRun>();
+}
@@ -0,0 +1,402 @@
+use crate::input::InputBindings;
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, PresentMode, PrimaryWindow, WindowMode};
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const SETTINGS_FILE: &str = "settings.toml";
+/// Directory created inside the platform config directory.
+const SETTINGS_DIR: &str = "survivors";
+/// Window sizes the settings screen cycles through.
+const RESOLUTIONS: [(f32, f32); 5] = [
+    (1280.0, 720.0),
+    (1600.0, 900.0),
+    (1920.0, 1080.0),
+    (2560.0, 1440.0),
+    (1200.0, 800.0),
+];
+/// Frame rate caps the settings screen cycles through, `None` leaving it uncapped.
+const FRAME_RATE_CAPS: [Option<u32>; 5] = [None, Some(30), Some(60), Some(120), Some(144)];
+/// Past this a stick would need pushing to its rim before it moved the player at all.
+const MAX_STICK_DEADZONE: f32 = 0.95;
+/// UI scales the settings screen cycles through.
+const UI_SCALES: [f32; 5] = [0.75, 1.0, 1.25, 1.5, 2.0];
+
+/// Player preferences, kept apart from the balance values in `Config`.
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub aim_mode: AimMode,
+    pub stick_deadzone: f32,
+    pub damage_numbers: bool,
+    pub hit_flash: bool,
+    pub screen_shake: bool,
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub display: DisplaySettings,
+    pub bindings: InputBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            aim_mode: AimMode::Mouse,
+            stick_deadzone: 0.25,
+            damage_numbers: true,
+            hit_flash: true,
+            screen_shake: true,
+            master_volume: 1.0,
+            music_volume: 0.6,
+            sfx_volume: 0.8,
+            display: DisplaySettings::default(),
+            bindings: InputBindings::default(),
+        }
+    }
+}
+
+/// How the window is shown, applied again whenever it changes in game.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub width: f32,
+    pub height: f32,
+    pub mode: DisplayMode,
+    pub vsync: bool,
+    pub frame_rate_cap: Option<u32>,
+    pub ui_scale: f32,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            width: 1920.0,
+            height: 1080.0,
+            mode: DisplayMode::Windowed,
+            vsync: true,
+            frame_rate_cap: None,
+            ui_scale: 1.0,
+        }
+    }
+}
+
+impl DisplaySettings {
+    pub fn window_mode(&self) -> WindowMode {
+        match self.mode {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+            // keeps the chosen resolution rather than switching to the monitor's
+            DisplayMode::Fullscreen => WindowMode::SizedFullscreen(MonitorSelection::Current),
+        }
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+
+    /// The shortest a frame may take, if the frame rate is capped.
+    pub fn frame_time(&self) -> Option<Duration> {
+        self.frame_rate_cap
+            .filter(|&fps| fps > 0)
+            .map(|fps| Duration::from_secs_f64(1.0 / fps as f64))
+    }
+
+    /// Moves on to the next preset size, starting over from the first one after a custom size.
+    pub fn next_resolution(&mut self) {
+        (self.width, self.height) = next_preset(&RESOLUTIONS, (self.width, self.height));
+    }
+
+    pub fn next_frame_rate_cap(&mut self) {
+        self.frame_rate_cap = next_preset(&FRAME_RATE_CAPS, self.frame_rate_cap);
+    }
+
+    pub fn next_ui_scale(&mut self) {
+        self.ui_scale = next_preset(&UI_SCALES, self.ui_scale);
+    }
+}
+
+/// The preset after `current`, or the first one if `current` was set by hand.
+fn next_preset<T: Copy + PartialEq>(presets: &[T], current: T) -> T {
+    match presets.iter().position(|&preset| preset == current) {
+        Some(index) => presets[(index + 1) % presets.len()],
+        None => presets[0],
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayMode {
+    Windowed,
+    /// A borderless window covering the whole monitor.
+    Borderless,
+    /// Exclusive fullscreen at the chosen resolution.
+    Fullscreen,
+}
+
+impl DisplayMode {
+    pub fn next(self) -> Self {
+        match self {
+            DisplayMode::Windowed => DisplayMode::Borderless,
+            DisplayMode::Borderless => DisplayMode::Fullscreen,
+            DisplayMode::Fullscreen => DisplayMode::Windowed,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings file, falling back to the one older versions kept in the working directory.
+    pub fn load() -> Self {
+        let mut settings: Settings = read_user_toml(SETTINGS_FILE)
+            .or_else(|| read_toml(Path::new(SETTINGS_FILE)))
+            .unwrap_or_default();
+        settings.bindings.fill_missing();
+        // a deadzone of one or more would divide by zero when rescaling stick input
+        settings.stick_deadzone = if settings.stick_deadzone.is_nan() {
+            Settings::default().stick_deadzone
+        } else {
+            settings.stick_deadzone.clamp(0.0, MAX_STICK_DEADZONE)
+        };
+        settings
+    }
+
+    pub fn volume(&self, bus: VolumeBus) -> f32 {
+        match bus {
+            VolumeBus::Master => self.master_volume,
+            VolumeBus::Music => self.music_volume,
+            VolumeBus::Sfx => self.sfx_volume,
+        }
+    }
+
+    /// How loud a bus plays once the master volume is applied.
+    pub fn effective_volume(&self, bus: VolumeBus) -> f32 {
+        match bus {
+            VolumeBus::Master => self.master_volume,
+            VolumeBus::Music | VolumeBus::Sfx => self.master_volume * self.volume(bus),
+        }
+    }
+
+    pub fn volume_mut(&mut self, bus: VolumeBus) -> &mut f32 {
+        match bus {
+            VolumeBus::Master => &mut self.master_volume,
+            VolumeBus::Music => &mut self.music_volume,
+            VolumeBus::Sfx => &mut self.sfx_volume,
+        }
+    }
+
+    pub fn save(&self) {
+        write_user_toml(SETTINGS_FILE, self);
+    }
+}
+
+/// `name` in the game's platform config directory, or the working directory without one.
+pub fn user_file(name: &str) -> PathBuf {
+    config_dir()
+        .map(|dir| dir.join(SETTINGS_DIR))
+        .unwrap_or_default()
+        .join(name)
+}
+
+/// Reads the user file `name`, or `None` if it is missing or broken.
+pub fn read_user_toml<T: DeserializeOwned>(name: &str) -> Option<T> {
+    read_toml(&user_file(name))
+}
+
+/// Writes `value` to the user file `name`, warning rather than failing if it can't.
+pub fn write_user_toml<T: Serialize>(name: &str, value: &T) {
+    let path = user_file(name);
+    let result = toml::to_string_pretty(value)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| write_atomically(&path, &contents).map_err(|err| err.to_string()));
+    if let Err(err) = result {
+        warn!("Cannot save {}: {err}", path.display());
+    }
+}
+
+/// A file that doesn't parse is moved aside to `.bak` so the next save can't overwrite it.
+fn read_toml<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let contents = fs::read_to_string(path).ok()?;
+    match toml::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            let mut backup = OsString::from(path);
+            backup.push(".bak");
+            let backup = PathBuf::from(backup);
+            match fs::rename(path, &backup) {
+                Ok(()) => warn!(
+                    "Cannot parse {}, moved it to {} and starting afresh: {err}",
+                    path.display(),
+                    backup.display()
+                ),
+                Err(rename_err) => warn!(
+                    "Cannot parse {} or move it aside ({rename_err}), starting afresh: {err}",
+                    path.display()
+                ),
+            }
+            None
+        }
+    }
+}
+
+/// Reads a file written with a `version`, turning away one from another version of the game.
+///
+/// The version is read ahead of the rest, so such a file is reported as that and not as broken,
+/// and a file from before versioning has none at all and reads as zero.
+pub fn load_versioned<T: DeserializeOwned>(path: &Path, version: u32) -> Result<T, String> {
+    #[derive(Deserialize)]
+    struct Header {
+        #[serde(default)]
+        version: u32,
+    }
+
+    let path_name = path.display();
+    let contents =
+        fs::read_to_string(path).map_err(|err| format!("cannot read {path_name}: {err}"))?;
+    let header: Header =
+        toml::from_str(&contents).map_err(|err| format!("cannot parse {path_name}: {err}"))?;
+    if header.version != version {
+        return Err(format!(
+            "{path_name} is from {} version of the game",
+            if header.version < version {
+                "an older"
+            } else {
+                "a newer"
+            }
+        ));
+    }
+    toml::from_str(&contents).map_err(|err| format!("cannot parse {path_name}: {err}"))
+}
+
+fn config_dir() -> Option<PathBuf> {
+    let env_path = |name| {
+        env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    if cfg!(target_os = "windows") {
+        env_path("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_path("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env_path("XDG_CONFIG_HOME").or_else(|| env_path("HOME").map(|home| home.join(".config")))
+    }
+}
+
+/// Writes next to the target and renames over it, so a crash never leaves half a file behind.
+pub fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp_path = path.with_extension("toml.tmp");
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AimMode {
+    /// Aim at the cursor and fire while `Fire` is held.
+    Mouse,
+    /// Aim at the nearest enemy and fire whenever one is in range.
+    Auto,
+    /// Aim with the right stick and fire while it is pushed or `Fire` is held.
+    Gamepad,
+}
+
+impl AimMode {
+    pub fn next(self) -> Self {
+        match self {
+            AimMode::Mouse => AimMode::Auto,
+            AimMode::Auto => AimMode::Gamepad,
+            AimMode::Gamepad => AimMode::Mouse,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum VolumeBus {
+    Master,
+    Music,
+    Sfx,
+}
+
+impl VolumeBus {
+    pub const ALL: [VolumeBus; 3] = [VolumeBus::Master, VolumeBus::Music, VolumeBus::Sfx];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VolumeBus::Master => "Master",
+            VolumeBus::Music => "Music",
+            VolumeBus::Sfx => "SFX",
+        }
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (apply_display_settings, apply_ui_scale).run_if(resource_changed::<Settings>),
+        );
+        // a browser paces frames itself, and a thread there cannot sleep
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(
+            Last,
+            limit_frame_rate.run_if(any_with_component::<PrimaryWindow>),
+        );
+    }
+}
+
+fn apply_display_settings(
+    settings: Res<Settings>,
+    mut last_display: Local<Option<DisplaySettings>>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    // the window opens with any launch options on top, so only later edits are applied, and
+    // other settings changing must not undo a window the player has resized by hand
+    let display = settings.display;
+    let previous = last_display.replace(display);
+    if previous.is_none_or(|previous| previous == display) || window_query.is_empty() {
+        return;
+    }
+
+    let mut window = window_query.single_mut();
+    window.resolution.set(display.width, display.height);
+    window.mode = display.window_mode();
+    window.present_mode = display.present_mode();
+}
+
+fn apply_ui_scale(settings: Res<Settings>, mut ui_scale: ResMut<UiScale>) {
+    let scale = settings.display.ui_scale.clamp(0.5, 3.0);
+    if ui_scale.0 != scale {
+        ui_scale.0 = scale;
+    }
+}
+
+/// Sleeps off whatever is left of the frame once everything else in it has run.
+#[cfg(not(target_arch = "wasm32"))]
+fn limit_frame_rate(
+    settings: Res<Settings>,
+    mut last_frame_end: Local<Option<std::time::Instant>>,
+) {
+    if let (Some(frame_time), Some(last_frame_end)) =
+        (settings.display.frame_time(), *last_frame_end)
+    {
+        let elapsed = last_frame_end.elapsed();
+        if elapsed < frame_time {
+            std::thread::sleep(frame_time - elapsed);
+        }
+    }
+    *last_frame_end = Some(std::time::Instant::now());
+}
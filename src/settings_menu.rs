@@ -0,0 +1,395 @@
+use crate::input::{Action, ActionInput, InputBindings};
+use crate::menu::{spawn_menu_button, MenuButton, MenuButtonActivated, MenuInputCaptured, MenuSet};
+use crate::settings::{Settings, VolumeBus};
+use crate::state::{GameState, InSettings};
+use bevy::prelude::*;
+
+#[derive(Component, Clone, Copy)]
+enum SettingsButton {
+    AimMode,
+    DamageNumbers,
+    HitFlash,
+    ScreenShake,
+    Resolution,
+    DisplayMode,
+    Vsync,
+    FrameRateCap,
+    UiScale,
+    Volume(VolumeBus),
+    Bind(Action, usize),
+    ResetControls,
+    Back,
+}
+
+#[derive(Component)]
+struct SettingsStatus;
+
+/// The action slot waiting for its next button press.
+#[derive(Resource)]
+struct Rebinding {
+    action: Action,
+    slot: usize,
+}
+
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(InSettings), spawn_settings_menu)
+            .add_systems(OnExit(InSettings), cancel_rebinding)
+            .add_systems(
+                Update,
+                (
+                    handle_settings_buttons.after(MenuSet),
+                    close_settings
+                        .before(capture_rebinding)
+                        .run_if(not(resource_exists::<Rebinding>)),
+                    capture_rebinding
+                        .after(handle_settings_buttons)
+                        .run_if(resource_exists::<Rebinding>),
+                    refresh_settings_labels.after(capture_rebinding),
+                )
+                    .run_if(in_state(InSettings)),
+            );
+    }
+}
+
+/// The screen to go back to when the settings are closed.
+fn return_state(state: &GameState) -> GameState {
+    match state {
+        GameState::Settings { in_run: true } => GameState::Paused,
+        _ => GameState::MainMenu,
+    }
+}
+
+fn close_settings(
+    actions: ActionInput,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if actions.just_pressed(Action::Cancel) {
+        next_state.set(return_state(state.get()));
+    }
+}
+
+fn spawn_settings_menu(mut commands: Commands) {
+    commands
+        .spawn((
+            StateScoped(InSettings),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new("Settings"),
+                TextFont {
+                    font_size: 36.0,
+                    ..default()
+                },
+            ));
+
+            root.spawn(Node {
+                column_gap: Val::Px(8.0),
+                ..default()
+            })
+            .with_children(|options| {
+                for (column, button) in [
+                    SettingsButton::AimMode,
+                    SettingsButton::DamageNumbers,
+                    SettingsButton::HitFlash,
+                    SettingsButton::ScreenShake,
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    spawn_menu_button(options, "", MenuButton { row: 0, column }, button);
+                }
+            });
+
+            root.spawn(Node {
+                column_gap: Val::Px(8.0),
+                ..default()
+            })
+            .with_children(|display| {
+                for (column, button) in [
+                    SettingsButton::Resolution,
+                    SettingsButton::DisplayMode,
+                    SettingsButton::Vsync,
+                    SettingsButton::FrameRateCap,
+                    SettingsButton::UiScale,
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    spawn_menu_button(display, "", MenuButton { row: 1, column }, button);
+                }
+            });
+
+            root.spawn(Node {
+                column_gap: Val::Px(8.0),
+                ..default()
+            })
+            .with_children(|volumes| {
+                for (column, bus) in VolumeBus::ALL.into_iter().enumerate() {
+                    spawn_menu_button(
+                        volumes,
+                        "",
+                        MenuButton { row: 2, column },
+                        SettingsButton::Volume(bus),
+                    );
+                }
+            });
+
+            for (row, action) in Action::ALL.into_iter().enumerate() {
+                root.spawn(Node {
+                    column_gap: Val::Px(8.0),
+                    align_items: AlignItems::Center,
+                    ..default()
+                })
+                .with_children(|action_row| {
+                    action_row.spawn((
+                        Text::new(action.label()),
+                        Node {
+                            width: Val::Px(140.0),
+                            ..default()
+                        },
+                    ));
+                    for slot in 0..InputBindings::SLOTS {
+                        spawn_menu_button(
+                            action_row,
+                            "",
+                            MenuButton {
+                                row: row + 3,
+                                column: slot,
+                            },
+                            SettingsButton::Bind(action, slot),
+                        );
+                    }
+                });
+            }
+
+            root.spawn(Node {
+                column_gap: Val::Px(8.0),
+                ..default()
+            })
+            .with_children(|footer| {
+                let row = Action::ALL.len() + 3;
+                spawn_menu_button(
+                    footer,
+                    "Reset Controls",
+                    MenuButton { row, column: 0 },
+                    SettingsButton::ResetControls,
+                );
+                spawn_menu_button(
+                    footer,
+                    "Back",
+                    MenuButton { row, column: 1 },
+                    SettingsButton::Back,
+                );
+            });
+
+            root.spawn((SettingsStatus, Text::new("")));
+        });
+}
+
+fn handle_settings_buttons(
+    mut commands: Commands,
+    mut activated_events: EventReader<MenuButtonActivated>,
+    button_query: Query<&SettingsButton>,
+    mut status_query: Query<&mut Text, With<SettingsStatus>>,
+    mut settings: ResMut<Settings>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in activated_events.read() {
+        let Ok(button) = button_query.get(event.0) else {
+            continue;
+        };
+
+        match *button {
+            SettingsButton::AimMode => {
+                settings.aim_mode = settings.aim_mode.next();
+                settings.save();
+            }
+            SettingsButton::DamageNumbers => {
+                settings.damage_numbers = !settings.damage_numbers;
+                settings.save();
+            }
+            SettingsButton::HitFlash => {
+                settings.hit_flash = !settings.hit_flash;
+                settings.save();
+            }
+            SettingsButton::ScreenShake => {
+                settings.screen_shake = !settings.screen_shake;
+                settings.save();
+            }
+            SettingsButton::Resolution => {
+                settings.display.next_resolution();
+                settings.save();
+            }
+            SettingsButton::DisplayMode => {
+                settings.display.mode = settings.display.mode.next();
+                settings.save();
+            }
+            SettingsButton::Vsync => {
+                settings.display.vsync = !settings.display.vsync;
+                settings.save();
+            }
+            SettingsButton::FrameRateCap => {
+                settings.display.next_frame_rate_cap();
+                settings.save();
+            }
+            SettingsButton::UiScale => {
+                settings.display.next_ui_scale();
+                settings.save();
+            }
+            SettingsButton::Volume(bus) => {
+                // steps up in tenths and wraps back round to silent
+                let volume = settings.volume_mut(bus);
+                *volume = if *volume >= 0.95 {
+                    0.0
+                } else {
+                    ((*volume + 0.1) * 10.0).round() / 10.0
+                };
+                settings.save();
+            }
+            SettingsButton::Bind(action, slot) => {
+                commands.insert_resource(Rebinding { action, slot });
+                commands.insert_resource(MenuInputCaptured);
+                set_status(
+                    &mut status_query,
+                    format!("Press a key or button for {}", action.label()),
+                );
+            }
+            SettingsButton::ResetControls => {
+                settings.bindings = InputBindings::default();
+                settings.save();
+                set_status(&mut status_query, "Controls reset to defaults");
+            }
+            SettingsButton::Back => next_state.set(return_state(state.get())),
+        }
+    }
+}
+
+fn capture_rebinding(
+    mut commands: Commands,
+    actions: ActionInput,
+    rebinding: Res<Rebinding>,
+    mut status_query: Query<&mut Text, With<SettingsStatus>>,
+    mut settings: ResMut<Settings>,
+) {
+    // the press that opened the prompt must not bind itself
+    if rebinding.is_added() {
+        return;
+    }
+
+    let Some(binding) = actions.any_just_pressed() else {
+        return;
+    };
+
+    match settings
+        .bindings
+        .bind(rebinding.action, rebinding.slot, binding)
+    {
+        Ok(()) => {
+            settings.save();
+            set_status(&mut status_query, "");
+        }
+        Err(other) => set_status(
+            &mut status_query,
+            format!("{binding} is already bound to {}", other.label()),
+        ),
+    }
+
+    commands.remove_resource::<Rebinding>();
+    commands.remove_resource::<MenuInputCaptured>();
+}
+
+fn cancel_rebinding(mut commands: Commands) {
+    commands.remove_resource::<Rebinding>();
+    commands.remove_resource::<MenuInputCaptured>();
+}
+
+fn refresh_settings_labels(
+    settings: Res<Settings>,
+    rebinding: Option<Res<Rebinding>>,
+    button_query: Query<(&SettingsButton, &Children)>,
+    mut text_query: Query<&mut Text, Without<SettingsStatus>>,
+) {
+    for (button, children) in button_query.iter() {
+        let label = match *button {
+            SettingsButton::AimMode => format!("Aim: {:?}", settings.aim_mode),
+            SettingsButton::DamageNumbers => {
+                format!("Damage Numbers: {}", on_off(settings.damage_numbers))
+            }
+            SettingsButton::HitFlash => format!("Hit Flash: {}", on_off(settings.hit_flash)),
+            SettingsButton::ScreenShake => {
+                format!("Screen Shake: {}", on_off(settings.screen_shake))
+            }
+            SettingsButton::Resolution => format!(
+                "Resolution: {}x{}",
+                settings.display.width, settings.display.height
+            ),
+            SettingsButton::DisplayMode => format!("Display: {:?}", settings.display.mode),
+            SettingsButton::Vsync => format!("VSync: {}", on_off(settings.display.vsync)),
+            SettingsButton::FrameRateCap => match settings.display.frame_rate_cap {
+                Some(fps) => format!("FPS Cap: {fps}"),
+                None => "FPS Cap: Off".to_string(),
+            },
+            SettingsButton::UiScale => format!("UI Scale: {}%", settings.display.ui_scale * 100.0),
+            SettingsButton::Volume(bus) => format!(
+                "{}: {}%",
+                bus.label(),
+                (settings.volume(bus) * 100.0).round()
+            ),
+            SettingsButton::Bind(action, slot) => {
+                if rebinding
+                    .as_ref()
+                    .is_some_and(|rebinding| rebinding.action == action && rebinding.slot == slot)
+                {
+                    "...".to_string()
+                } else {
+                    settings
+                        .bindings
+                        .slot(action, slot)
+                        .map_or("-".to_string(), |binding| binding.to_string())
+                }
+            }
+            SettingsButton::ResetControls | SettingsButton::Back => continue,
+        };
+
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                if text.0 != label {
+                    text.0 = label.clone();
+                }
+            }
+        }
+    }
+}
+
+fn set_status(
+    status_query: &mut Query<&mut Text, With<SettingsStatus>>,
+    status: impl Into<String>,
+) {
+    let status = status.into();
+    for mut text in status_query.iter_mut() {
+        text.0 = status.clone();
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "On"
+    } else {
+        "Off"
+    }
+}
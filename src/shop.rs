@@ -0,0 +1,136 @@
+use crate::config::Config;
+use crate::input::{Action, ActionInput};
+use crate::menu::{spawn_menu_button, MenuButton, MenuButtonActivated, MenuSet};
+use crate::profile::{PermanentUpgrade, Profile};
+use crate::state::GameState;
+use bevy::prelude::*;
+
+#[derive(Component, Clone, Copy)]
+enum ShopButton {
+    Buy(PermanentUpgrade),
+    Back,
+}
+
+#[derive(Component)]
+struct ShopGold;
+
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Shop), spawn_shop)
+            .add_systems(
+                Update,
+                (
+                    handle_shop_buttons.after(MenuSet),
+                    close_shop,
+                    refresh_shop_labels.after(handle_shop_buttons),
+                )
+                    .run_if(in_state(GameState::Shop)),
+            );
+    }
+}
+
+fn spawn_shop(mut commands: Commands) {
+    commands
+        .spawn((
+            StateScoped(GameState::Shop),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new("Shop"),
+                TextFont {
+                    font_size: 36.0,
+                    ..default()
+                },
+            ));
+            root.spawn((ShopGold, Text::new("")));
+
+            for (row, upgrade) in PermanentUpgrade::ALL.into_iter().enumerate() {
+                spawn_menu_button(
+                    root,
+                    "",
+                    MenuButton { row, column: 0 },
+                    ShopButton::Buy(upgrade),
+                );
+            }
+            spawn_menu_button(
+                root,
+                "Back",
+                MenuButton {
+                    row: PermanentUpgrade::ALL.len(),
+                    column: 0,
+                },
+                ShopButton::Back,
+            );
+        });
+}
+
+fn close_shop(actions: ActionInput, mut next_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Cancel) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn handle_shop_buttons(
+    config: Res<Config>,
+    mut activated_events: EventReader<MenuButtonActivated>,
+    button_query: Query<&ShopButton>,
+    mut profile: ResMut<Profile>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in activated_events.read() {
+        match button_query.get(event.0) {
+            Ok(ShopButton::Buy(upgrade)) => {
+                profile.buy(*upgrade, &config.shop);
+            }
+            Ok(ShopButton::Back) => next_state.set(GameState::MainMenu),
+            Err(_) => {}
+        }
+    }
+}
+
+fn refresh_shop_labels(
+    config: Res<Config>,
+    profile: Res<Profile>,
+    button_query: Query<(&ShopButton, &Children)>,
+    mut text_query: Query<&mut Text, Without<ShopGold>>,
+    mut gold_query: Query<&mut Text, With<ShopGold>>,
+) {
+    for mut text in gold_query.iter_mut() {
+        let label = format!("{} gold", profile.gold);
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+
+    for (button, children) in button_query.iter() {
+        let ShopButton::Buy(upgrade) = *button else {
+            continue;
+        };
+        let level = profile.level(upgrade, &config.shop);
+        let max_level = upgrade.item(&config.shop).max_level;
+        let label = match profile.cost(upgrade, &config.shop) {
+            Some(cost) => format!("{} {level}/{max_level}: {cost} gold", upgrade.label()),
+            None => format!("{} {level}/{max_level}: Maxed", upgrade.label()),
+        };
+
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                if text.0 != label {
+                    text.0 = label.clone();
+                }
+            }
+        }
+    }
+}
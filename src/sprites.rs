@@ -0,0 +1,225 @@
+use crate::animation::AnimationState;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Sprites the game looks up by name, so a manifest missing one fails on the loading screen.
+const REQUIRED_SPRITES: &[&str] = &[
+    "staff",
+    "arcane_staff",
+    "bolt",
+    "violet_bolt",
+    "missile",
+    "swarm_missile",
+    "thunder_bolt",
+    "xp_orb",
+    "gold_coin",
+    "particle",
+    "decoration",
+];
+const REQUIRED_ANIMATIONS: &[&str] = &[
+    "player_idle",
+    "player_run",
+    "player_hurt",
+    "player_die",
+    "enemy_idle",
+    "enemy_run",
+    "enemy_hurt",
+    "enemy_die",
+];
+
+/// Names every sprite and animation clip on the spritesheet.
+#[derive(Asset, TypePath, Deserialize, Clone)]
+pub struct SpriteManifest {
+    pub image: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: u32,
+    pub rows: u32,
+    #[serde(default)]
+    pub sprites: HashMap<String, SpriteCell>,
+    #[serde(default)]
+    pub animations: HashMap<String, AnimationClip>,
+    #[serde(skip)]
+    pub image_handle: Handle<Image>,
+    #[serde(skip)]
+    pub layout_handle: Handle<TextureAtlasLayout>,
+}
+
+/// A single cell, or `variants` interchangeable cells next to each other on a row.
+#[derive(Deserialize, Clone, Copy)]
+pub struct SpriteCell {
+    pub row: u32,
+    pub column: u32,
+    #[serde(default = "one")]
+    pub variants: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// Consecutive frames on a row, starting at `column`.
+#[derive(Deserialize, Clone, Copy)]
+pub struct AnimationClip {
+    pub row: u32,
+    pub column: u32,
+    pub frames: u32,
+    pub fps: f32,
+    #[serde(default)]
+    pub mode: LoopMode,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopMode {
+    #[default]
+    Loop,
+    Once,
+}
+
+impl Default for AnimationClip {
+    fn default() -> Self {
+        Self {
+            row: 0,
+            column: 0,
+            frames: 1,
+            fps: 1.0,
+            mode: LoopMode::Loop,
+        }
+    }
+}
+
+impl SpriteManifest {
+    pub fn tile_size(&self) -> UVec2 {
+        UVec2::new(self.tile_width, self.tile_height)
+    }
+
+    pub fn index(&self, row: u32, column: u32) -> usize {
+        (row * self.columns + column) as usize
+    }
+
+    /// Checks every entry against the grid, and the grid against the loaded image, along with
+    /// the clips of every character sprite the config names.
+    pub fn validate<'a>(
+        &self,
+        image_size: UVec2,
+        character_sprites: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), String> {
+        let grid_size = self.tile_size() * UVec2::new(self.columns, self.rows);
+        if grid_size.x > image_size.x || grid_size.y > image_size.y {
+            return Err(format!(
+                "{} is {}x{} but the manifest grid needs {}x{}",
+                self.image, image_size.x, image_size.y, grid_size.x, grid_size.y
+            ));
+        }
+
+        for name in REQUIRED_SPRITES {
+            if !self.sprites.contains_key(*name) {
+                return Err(format!("sprite `{name}` is missing"));
+            }
+        }
+        for name in REQUIRED_ANIMATIONS {
+            if !self.animations.contains_key(*name) {
+                return Err(format!("animation `{name}` is missing"));
+            }
+        }
+        for sprite in character_sprites {
+            for state in AnimationState::ALL {
+                let name = format!("{sprite}_{}", state.clip_suffix());
+                if !self.animations.contains_key(&name) {
+                    return Err(format!("animation `{name}` is missing"));
+                }
+            }
+        }
+
+        for (name, cell) in self.sprites.iter() {
+            if cell.variants == 0 || !self.fits(cell.row, cell.column, cell.variants) {
+                return Err(format!("sprite `{name}` is outside the grid"));
+            }
+        }
+        for (name, clip) in self.animations.iter() {
+            if clip.frames == 0 || !self.fits(clip.row, clip.column, clip.frames) {
+                return Err(format!("animation `{name}` is outside the grid"));
+            }
+            if clip.fps <= 0.0 {
+                return Err(format!("animation `{name}` needs a positive fps"));
+            }
+        }
+        Ok(())
+    }
+
+    fn fits(&self, row: u32, column: u32, count: u32) -> bool {
+        row < self.rows && column + count <= self.columns
+    }
+}
+
+#[derive(Debug)]
+pub enum SpriteManifestError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for SpriteManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpriteManifestError::Io(err) => write!(f, "cannot read sprite manifest: {err}"),
+            SpriteManifestError::Parse(err) => write!(f, "cannot parse sprite manifest: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SpriteManifestError {}
+
+#[derive(Default)]
+struct SpriteManifestLoader;
+
+impl AssetLoader for SpriteManifestLoader {
+    type Asset = SpriteManifest;
+    type Settings = ();
+    type Error = SpriteManifestError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<SpriteManifest, SpriteManifestError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(SpriteManifestError::Io)?;
+        let manifest_str = String::from_utf8_lossy(&bytes);
+        let mut manifest: SpriteManifest =
+            toml::from_str(&manifest_str).map_err(SpriteManifestError::Parse)?;
+        manifest.image_handle = load_context.load(manifest.image.clone());
+        manifest.layout_handle = load_context.add_labeled_asset(
+            "layout".to_string(),
+            TextureAtlasLayout::from_grid(
+                manifest.tile_size(),
+                manifest.columns,
+                manifest.rows,
+                None,
+                None,
+            ),
+        );
+        Ok(manifest)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["manifest.toml"]
+    }
+}
+
+pub struct SpriteManifestPlugin;
+
+impl Plugin for SpriteManifestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SpriteManifest>()
+            .init_asset_loader::<SpriteManifestLoader>();
+    }
+}
@@ -1,10 +1,74 @@
-use bevy::prelude::States;
+use bevy::prelude::{ComputedStates, Resource, StateScoped, States};
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
 pub enum GameState {
     #[default]
     Loading,
+    MainMenu,
+    CharacterSelect,
     Initializing,
     Gaming,
+    Paused,
+    /// `in_run` when opened from the pause menu rather than the main menu.
+    Settings {
+        in_run: bool,
+    },
+    Shop,
+    HighScores,
     Dying,
+    GameOver,
+}
+
+/// Where the game goes once assets have loaded.
+#[derive(Resource, Clone, Copy)]
+pub struct StartState(pub GameState);
+
+impl Default for StartState {
+    fn default() -> Self {
+        Self(GameState::MainMenu)
+    }
+}
+
+/// Exists for as long as a run is in progress; gameplay entities are scoped to it.
+///
+/// `Initializing` sits outside the run so restarting from `GameOver` still clears the old one.
+/// `Settings` only counts as part of the run when opened from the pause menu, so the run
+/// survives a trip into them and nothing of one shows behind them on the main menu.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct InRun;
+
+impl ComputedStates for InRun {
+    type SourceStates = GameState;
+
+    fn compute(game_state: GameState) -> Option<Self> {
+        match game_state {
+            GameState::Loading
+            | GameState::MainMenu
+            | GameState::CharacterSelect
+            | GameState::Shop
+            | GameState::HighScores
+            | GameState::Initializing => None,
+            GameState::Settings { in_run } => in_run.then_some(InRun),
+            GameState::Gaming | GameState::Paused | GameState::Dying | GameState::GameOver => {
+                Some(InRun)
+            }
+        }
+    }
+}
+
+/// Exists while the settings screen is open, wherever it was opened from.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct InSettings;
+
+impl ComputedStates for InSettings {
+    type SourceStates = GameState;
+
+    fn compute(game_state: GameState) -> Option<Self> {
+        matches!(game_state, GameState::Settings { .. }).then_some(InSettings)
+    }
+}
+
+/// Required by every gameplay component, so nothing spawned for a run outlives it.
+pub fn run_scoped() -> StateScoped<InRun> {
+    StateScoped(InRun)
 }
@@ -1,69 +1,262 @@
-use crate::config::CONFIG;
-use crate::player::Player;
+use crate::audio::{PlaySound, SoundEffect};
+use crate::camera::ShakeCamera;
+use crate::collision::Damaged;
+use crate::config::{Config, WeaponConfig};
+use crate::enemy::{nearest_enemies, nearest_enemy, Enemy};
+use crate::input::{Action, ActionInput};
+use crate::player::{ChosenCharacter, Player};
+use crate::replay::replaying;
 use crate::resources::{CursorPosition, GlobalTextureAtlas};
-use crate::state::GameState;
-use crate::util::get_sprite_index;
+use crate::rng::{GameRng, RngStream};
+use crate::save::ResumeRun;
+use crate::settings::{AimMode, Settings};
+use crate::state::{run_scoped, GameState, InRun};
 use bevy::app::{App, Plugin};
+use bevy::ecs::system::EntityCommands;
 use bevy::math::{vec2, vec3, Quat, Vec3};
 use bevy::prelude::*;
 use bevy::time::Stopwatch;
-use std::f32::consts::PI;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeaponKind {
+    Gun,
+    HomingMissile,
+    ChainLightning,
+    ArcaneStaff,
+    MissileSwarm,
+    Thunderstorm,
+}
+
+impl WeaponKind {
+    pub const ALL: [WeaponKind; 6] = [
+        WeaponKind::Gun,
+        WeaponKind::HomingMissile,
+        WeaponKind::ChainLightning,
+        WeaponKind::ArcaneStaff,
+        WeaponKind::MissileSwarm,
+        WeaponKind::Thunderstorm,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WeaponKind::Gun => "Gun",
+            WeaponKind::HomingMissile => "Homing Missile",
+            WeaponKind::ChainLightning => "Chain Lightning",
+            WeaponKind::ArcaneStaff => "Arcane Staff",
+            WeaponKind::MissileSwarm => "Missile Swarm",
+            WeaponKind::Thunderstorm => "Thunderstorm",
+        }
+    }
+
+    /// Whether the weapon is drawn in hand and fired by aiming, rather than firing on its own.
+    pub fn is_held(self) -> bool {
+        matches!(self, WeaponKind::Gun | WeaponKind::ArcaneStaff)
+    }
+
+    /// Manifest name of the sprite each shot is drawn with.
+    pub fn projectile_sprite_name(self) -> &'static str {
+        match self {
+            WeaponKind::ArcaneStaff => "violet_bolt",
+            WeaponKind::HomingMissile => "missile",
+            WeaponKind::MissileSwarm => "swarm_missile",
+            _ => "bolt",
+        }
+    }
+
+    /// Manifest name of the sprite shown in hand, only meaningful for held weapons.
+    pub fn sprite_name(self) -> &'static str {
+        match self {
+            WeaponKind::ArcaneStaff => "arcane_staff",
+            _ => "staff",
+        }
+    }
+}
 
 #[derive(Component)]
-pub struct Weapon;
+#[require(StateScoped<InRun>(run_scoped))]
+pub struct Weapon {
+    pub kind: WeaponKind,
+    pub level: u32,
+}
+
+impl Weapon {
+    pub fn new(kind: WeaponKind) -> Self {
+        Self { kind, level: 1 }
+    }
+
+    pub fn damage_multiplier(&self, config: &WeaponConfig) -> f32 {
+        1.0 + config.damage_per_level * (self.level - 1) as f32
+    }
+
+    pub fn fire_rate_multiplier(&self, config: &WeaponConfig) -> f32 {
+        1.0 + config.fire_rate_per_level * (self.level - 1) as f32
+    }
+
+    pub fn projectile_count(&self, config: &WeaponConfig) -> usize {
+        1 + ((self.level - 1) / config.levels_per_extra_projectile) as usize
+    }
+
+    /// Whether this weapon is `kind` or has evolved from it.
+    fn is_or_evolved_from(&self, kind: WeaponKind, config: &WeaponConfig) -> bool {
+        self.kind == kind
+            || config
+                .evolutions
+                .iter()
+                .any(|recipe| recipe.weapon == kind && recipe.evolves_into == self.kind)
+    }
+}
+
+/// The weapon drawn next to the player and aimed at the cursor.
+#[derive(Component)]
+pub struct HeldWeapon;
 #[derive(Component)]
 pub struct WeaponTimer(pub Stopwatch);
 #[derive(Component)]
-pub struct Projectile;
+#[require(StateScoped<InRun>(run_scoped))]
+pub struct Projectile {
+    pub weapon: WeaponKind,
+    pub damage: f32,
+    pub speed: f32,
+}
+#[derive(Component)]
+pub struct ProjectileDirection(pub Vec3);
 #[derive(Component)]
-pub struct ProjectileDirection(Vec3);
+pub struct Homing {
+    pub target: Option<Entity>,
+}
+#[derive(Component)]
+#[require(StateScoped<InRun>(run_scoped))]
+pub struct LightningArc(Timer);
+
+/// Where the held weapon points and whether it should fire, whatever the aim mode.
+#[derive(Resource)]
+pub struct Aim {
+    pub direction: Vec2,
+    pub firing: bool,
+}
+
+impl Default for Aim {
+    fn default() -> Self {
+        Self {
+            direction: Vec2::X,
+            firing: false,
+        }
+    }
+}
 
 pub struct WeaponPlugin;
 
 impl Plugin for WeaponPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            init_weapon.run_if(in_state(GameState::Initializing)),
-        )
-        .add_systems(
-            Update,
-            (
-                update_weapon_transform,
-                handle_weapon_input,
-                update_projectiles,
+        app.init_resource::<Aim>()
+            .add_systems(
+                Update,
+                init_weapon
+                    .run_if(in_state(GameState::Initializing))
+                    .run_if(not(resource_exists::<ResumeRun>)),
             )
-                .run_if(in_state(GameState::Gaming)),
-        );
+            .add_systems(
+                Update,
+                (
+                    update_aim
+                        .before(update_weapon_transform)
+                        .run_if(not(replaying)),
+                    update_weapon_transform,
+                    tick_weapon_timers,
+                    handle_weapon_input
+                        .after(update_weapon_transform)
+                        .after(tick_weapon_timers),
+                    unlock_weapons,
+                    evolve_weapons,
+                    fire_homing_missiles.after(tick_weapon_timers),
+                    fire_chain_lightning.after(tick_weapon_timers),
+                    steer_homing_missiles.before(update_projectiles),
+                    update_projectiles,
+                    fade_lightning_arcs,
+                )
+                    .run_if(in_state(GameState::Gaming)),
+            );
     }
 }
 
 fn init_weapon(
     mut commands: Commands,
+    config: Res<Config>,
+    chosen_character: Res<ChosenCharacter>,
     texture_handle: Res<GlobalTextureAtlas>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    commands.spawn((
-        Weapon,
-        Sprite {
-            image: texture_handle.image.clone().unwrap(),
-            texture_atlas: Some(TextureAtlas {
-                layout: texture_handle.layout.clone().unwrap(),
-                index: get_sprite_index(5, 0),
-            }),
-            ..default()
-        },
-        Transform::from_scale(Vec3::splat(CONFIG.sprite.sprite_scale_factor)),
-        WeaponTimer(Stopwatch::new()),
-    ));
+    let kind = config
+        .character(chosen_character.0.as_deref())
+        .starting_weapon;
+    if kind.is_held() {
+        commands.spawn((
+            Weapon::new(kind),
+            HeldWeapon,
+            texture_handle.sprite(kind.sprite_name()),
+            Transform::from_scale(Vec3::splat(config.sprite.sprite_scale_factor)),
+            WeaponTimer(Stopwatch::new()),
+        ));
+    } else {
+        commands.spawn((Weapon::new(kind), WeaponTimer(Stopwatch::new())));
+    }
 
     next_state.set(GameState::Gaming);
 }
 
-fn update_weapon_transform(
+fn update_aim(
+    config: Res<Config>,
+    settings: Res<Settings>,
     cursor_position: Res<CursorPosition>,
+    actions: ActionInput,
     player_query: Query<&Transform, With<Player>>,
-    mut weapon_query: Query<&mut Transform, (With<Weapon>, Without<Player>)>,
+    enemy_query: Query<(Entity, &Transform), With<Enemy>>,
+    mut aim: ResMut<Aim>,
+) {
+    if player_query.is_empty() {
+        return;
+    }
+
+    let player_position = player_query.single().translation.truncate();
+    let (direction, firing) = match settings.aim_mode {
+        AimMode::Mouse => (
+            cursor_position.0.map(|cursor| cursor - player_position),
+            actions.pressed(Action::Fire),
+        ),
+        AimMode::Auto => {
+            let target = nearest_enemy(
+                enemy_query
+                    .iter()
+                    .map(|(entity, transform)| (entity, transform.translation.truncate())),
+                player_position,
+                config.player.auto_aim_range,
+            );
+            (
+                target.map(|(_, target_position)| target_position - player_position),
+                target.is_some(),
+            )
+        }
+        AimMode::Gamepad => {
+            let stick = actions.stick(Gamepad::right_stick);
+            (stick, stick.is_some() || actions.pressed(Action::Fire))
+        }
+    };
+
+    // keep pointing the last way we aimed when there is nothing to aim at
+    if let Some(direction) = direction.and_then(Vec2::try_normalize) {
+        aim.direction = direction;
+    }
+    aim.firing = firing;
+}
+
+fn update_weapon_transform(
+    aim: Res<Aim>,
+    player_query: Query<&Transform, With<Player>>,
+    mut weapon_query: Query<&mut Transform, (With<HeldWeapon>, Without<Player>)>,
 ) {
     if player_query.is_empty() || weapon_query.is_empty() {
         return;
@@ -71,10 +264,8 @@ fn update_weapon_transform(
 
     let player_position = player_query.single().translation.truncate();
     let mut weapon_transform = weapon_query.single_mut();
-    let cursor_position = cursor_position.0.unwrap_or(player_position);
 
-    let angle =
-        (player_position.y - cursor_position.y).atan2(player_position.x - cursor_position.x) + PI;
+    let angle = aim.direction.to_angle().rem_euclid(TAU);
 
     // mirrors weapon if it's on the other side
     if PI / 2.0 < angle && angle < 3.0 * PI / 2.0 {
@@ -92,28 +283,34 @@ fn update_weapon_transform(
     weapon_transform.translation = vec3(new_weapon_pos.x, new_weapon_pos.y, 10.0);
 }
 
+fn tick_weapon_timers(time: Res<Time>, mut timer_query: Query<&mut WeaponTimer>) {
+    for mut weapon_timer in timer_query.iter_mut() {
+        weapon_timer.0.tick(time.delta());
+    }
+}
+
 fn handle_weapon_input(
     mut commands: Commands,
-    time: Res<Time>,
-    mut weapon_query: Query<(&Transform, &mut WeaponTimer), With<Weapon>>,
+    config: Res<Config>,
+    mut weapon_query: Query<(&Transform, &Weapon, &mut WeaponTimer), With<HeldWeapon>>,
     player_query: Query<(&Transform, &Player), With<Player>>,
-    mouse_input: Res<ButtonInput<MouseButton>>,
+    aim: Res<Aim>,
     texture_handle: Res<GlobalTextureAtlas>,
 ) {
     if weapon_query.is_empty() || player_query.is_empty() {
         return;
     }
 
-    let (weapon_transform, mut weapon_timer) = weapon_query.single_mut();
+    let (weapon_transform, weapon, mut weapon_timer) = weapon_query.single_mut();
     let weapon_position = weapon_transform.translation.truncate();
-    weapon_timer.0.tick(time.delta());
 
     let (player_transform, player) = player_query.single();
     let player_position = player_transform.translation.truncate();
 
-    if !mouse_input.pressed(MouseButton::Left)
+    if !aim.firing
         || weapon_timer.0.elapsed_secs()
-            < CONFIG.player.attack_interval / player.attack_speed_multiplier
+            < config.player.attack_interval
+                / (player.attack_speed_multiplier * weapon.fire_rate_multiplier(&config.weapon))
     {
         return;
     }
@@ -124,34 +321,473 @@ fn handle_weapon_input(
     } else {
         weapon_transform.local_x()
     };
+    let aim_angle = projectile_direction.truncate().to_angle();
+
+    // the gun fans extra projectiles around the aim, the staff fires a ring around the player
+    let count = weapon.projectile_count(&config.weapon);
+    let angles: Vec<f32> = match weapon.kind {
+        WeaponKind::ArcaneStaff => {
+            let count = config.weapon.arcane_staff_projectiles + count - 1;
+            (0..count)
+                .map(|i| aim_angle + TAU * i as f32 / count as f32)
+                .collect()
+        }
+        _ => (0..count)
+            .map(|i| aim_angle + (i as f32 - (count - 1) as f32 / 2.0) * config.weapon.gun_spread)
+            .collect(),
+    };
 
     weapon_timer.0.reset();
+    commands.trigger(PlaySound(SoundEffect::Shoot));
+    for angle in angles {
+        spawn_projectile(
+            &mut commands,
+            &config,
+            weapon_position,
+            Vec2::from_angle(angle),
+            Projectile {
+                weapon: weapon.kind,
+                damage: config.player.projectile_damage
+                    * weapon.damage_multiplier(&config.weapon)
+                    * player.damage_multiplier,
+                speed: config.player.projectile_speed,
+            },
+            texture_handle.sprite(weapon.kind.projectile_sprite_name()),
+        );
+    }
+}
+
+pub fn spawn_projectile<'a>(
+    commands: &'a mut Commands,
+    config: &Config,
+    position: Vec2,
+    direction: Vec2,
+    projectile: Projectile,
+    sprite: Sprite,
+) -> EntityCommands<'a> {
     commands.spawn((
-        Projectile,
+        projectile,
+        sprite,
+        Transform::from_translation(position.extend(1.0))
+            .with_rotation(Quat::from_rotation_z(direction.to_angle()))
+            .with_scale(Vec3::splat(config.sprite.sprite_scale_factor)),
+        ProjectileDirection(direction.extend(0.0)),
+    ))
+}
+
+fn unlock_weapons(
+    mut commands: Commands,
+    config: Res<Config>,
+    player_query: Query<&Player, With<Player>>,
+    weapon_query: Query<&Weapon, With<Weapon>>,
+) {
+    if player_query.is_empty() {
+        return;
+    }
+
+    let player = player_query.single();
+    for (kind, unlock_level) in [
+        (
+            WeaponKind::HomingMissile,
+            config.weapon.homing_missile_unlock_level,
+        ),
+        (
+            WeaponKind::ChainLightning,
+            config.weapon.chain_lightning_unlock_level,
+        ),
+    ] {
+        if player.level >= unlock_level
+            && !weapon_query
+                .iter()
+                .any(|weapon| weapon.is_or_evolved_from(kind, &config.weapon))
+        {
+            commands.spawn((Weapon::new(kind), WeaponTimer(Stopwatch::new())));
+        }
+    }
+}
+
+fn evolve_weapons(
+    config: Res<Config>,
+    player_query: Query<&Player, With<Player>>,
+    mut weapon_query: Query<(&mut Weapon, Option<&mut Sprite>), With<Weapon>>,
+    texture_handle: Res<GlobalTextureAtlas>,
+) {
+    if player_query.is_empty() {
+        return;
+    }
+
+    let player = player_query.single();
+    for (mut weapon, sprite) in weapon_query.iter_mut() {
+        if weapon.level < config.weapon.max_level {
+            continue;
+        }
+
+        let Some(recipe) = config.weapon.evolutions.iter().find(|recipe| {
+            recipe.weapon == weapon.kind
+                && player.stat_upgrade_count(recipe.upgrade) >= recipe.upgrade_count
+        }) else {
+            continue;
+        };
+
+        info!("{:?} evolved into {:?}", weapon.kind, recipe.evolves_into);
+        weapon.kind = recipe.evolves_into;
+        if let Some(texture_atlas) =
+            sprite.and_then(|sprite| sprite.into_inner().texture_atlas.as_mut())
+        {
+            texture_atlas.index = texture_handle.index(weapon.kind.sprite_name(), 0);
+        }
+    }
+}
+
+fn fire_homing_missiles(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut weapon_query: Query<(&Weapon, &mut WeaponTimer), Without<HeldWeapon>>,
+    player_query: Query<(&Transform, &Player), With<Player>>,
+    enemy_query: Query<(Entity, &Transform), With<Enemy>>,
+    texture_handle: Res<GlobalTextureAtlas>,
+) {
+    if weapon_query.is_empty() || player_query.is_empty() {
+        return;
+    }
+
+    let (player_transform, player) = player_query.single();
+    let player_position = player_transform.translation.truncate();
+
+    for (weapon, mut weapon_timer) in weapon_query.iter_mut() {
+        // the swarm spreads its salvo over distinct targets instead of stacking on one
+        let salvo = match weapon.kind {
+            WeaponKind::HomingMissile => weapon.projectile_count(&config.weapon),
+            WeaponKind::MissileSwarm => {
+                config.weapon.missile_swarm_salvo + weapon.projectile_count(&config.weapon) - 1
+            }
+            _ => continue,
+        };
+
+        if weapon_timer.0.elapsed_secs()
+            < config.weapon.homing_missile_interval
+                / (player.attack_speed_multiplier * weapon.fire_rate_multiplier(&config.weapon))
+        {
+            continue;
+        }
+
+        // hold fire until something is in range so the shot isn't wasted
+        let targets = nearest_enemies(
+            enemy_query
+                .iter()
+                .map(|(entity, transform)| (entity, transform.translation.truncate())),
+            player_position,
+            config.weapon.homing_missile_range,
+            if weapon.kind == WeaponKind::MissileSwarm {
+                salvo
+            } else {
+                1
+            },
+        );
+        if targets.is_empty() {
+            continue;
+        }
+
+        weapon_timer.0.reset();
+        commands.trigger(PlaySound(SoundEffect::Shoot));
+        for (target, target_position) in targets.into_iter().cycle().take(salvo) {
+            spawn_projectile(
+                &mut commands,
+                &config,
+                player_position,
+                (target_position - player_position).normalize_or_zero(),
+                Projectile {
+                    weapon: weapon.kind,
+                    damage: config.weapon.homing_missile_damage
+                        * weapon.damage_multiplier(&config.weapon)
+                        * player.damage_multiplier,
+                    speed: config.weapon.homing_missile_speed,
+                },
+                texture_handle.sprite(weapon.kind.projectile_sprite_name()),
+            )
+            .insert(Homing {
+                target: Some(target),
+            });
+        }
+    }
+}
+
+fn steer_homing_missiles(
+    time: Res<Time>,
+    config: Res<Config>,
+    mut missile_query: Query<
+        (&mut Transform, &mut ProjectileDirection, &mut Homing),
+        Without<Enemy>,
+    >,
+    enemy_query: Query<(Entity, &Transform), With<Enemy>>,
+) {
+    if missile_query.is_empty() {
+        return;
+    }
+
+    for (mut transform, mut direction, mut homing) in missile_query.iter_mut() {
+        let position = transform.translation.truncate();
+
+        // previous target died, pick the closest survivor
+        let target_position = match homing
+            .target
+            .and_then(|target| enemy_query.get(target).ok())
+        {
+            Some((_, target_transform)) => Some(target_transform.translation.truncate()),
+            None => {
+                let nearest = nearest_enemy(
+                    enemy_query
+                        .iter()
+                        .map(|(entity, transform)| (entity, transform.translation.truncate())),
+                    position,
+                    config.weapon.homing_missile_range,
+                );
+                homing.target = nearest.map(|(entity, _)| entity);
+                nearest.map(|(_, position)| position)
+            }
+        };
+
+        let Some(target_position) = target_position else {
+            continue;
+        };
+
+        let current = direction.0.truncate().normalize_or_zero();
+        let desired = (target_position - position).normalize_or_zero();
+        let max_turn = config.weapon.homing_missile_turn_rate * time.delta_secs();
+        let turn = current.angle_to(desired).clamp(-max_turn, max_turn);
+        let steered = Vec2::from_angle(turn).rotate(current);
+
+        direction.0 = steered.extend(0.0);
+        transform.rotation = Quat::from_rotation_z(steered.to_angle());
+    }
+}
+
+fn fire_chain_lightning(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut weapon_query: Query<(&Weapon, &mut WeaponTimer), Without<HeldWeapon>>,
+    player_query: Query<(&Transform, &Player), With<Player>>,
+    mut enemy_query: Query<(Entity, &Transform, &mut Enemy), With<Enemy>>,
+    texture_handle: Res<GlobalTextureAtlas>,
+    mut rng: ResMut<GameRng>,
+) {
+    if weapon_query.is_empty() || player_query.is_empty() {
+        return;
+    }
+
+    let (player_transform, player) = player_query.single();
+    let player_position = player_transform.translation.truncate();
+
+    for (weapon, mut weapon_timer) in weapon_query.iter_mut() {
+        if weapon.kind != WeaponKind::ChainLightning && weapon.kind != WeaponKind::Thunderstorm {
+            continue;
+        }
+
+        if weapon_timer.0.elapsed_secs()
+            < config.weapon.chain_lightning_interval
+                / (player.attack_speed_multiplier * weapon.fire_rate_multiplier(&config.weapon))
+        {
+            continue;
+        }
+
+        let damage = config.weapon.chain_lightning_damage
+            * weapon.damage_multiplier(&config.weapon)
+            * player.damage_multiplier;
+        let crits = rng.stream(RngStream::LightningCrits);
+        let struck = if weapon.kind == WeaponKind::Thunderstorm {
+            let targets = nearest_enemies(
+                enemy_query
+                    .iter()
+                    .map(|(entity, transform, _)| (entity, transform.translation.truncate())),
+                player_position,
+                config.weapon.thunderstorm_range,
+                config.weapon.thunderstorm_strikes + weapon.projectile_count(&config.weapon) - 1,
+            );
+            call_thunderstorm(
+                &mut commands,
+                &texture_handle,
+                &config,
+                crits,
+                &mut enemy_query,
+                &targets,
+                damage,
+            )
+        } else {
+            chain_lightning(
+                &mut commands,
+                &config,
+                crits,
+                &mut enemy_query,
+                player_position,
+                config.weapon.chain_lightning_max_jumps + weapon.projectile_count(&config.weapon)
+                    - 1,
+                damage,
+            )
+        };
+
+        if struck {
+            weapon_timer.0.reset();
+            commands.trigger(PlaySound(SoundEffect::Shoot));
+        }
+    }
+}
+
+/// Jumps from the player to the nearest unstruck enemy, losing damage on every jump.
+fn chain_lightning(
+    commands: &mut Commands,
+    config: &Config,
+    crits: &mut impl Rng,
+    enemy_query: &mut Query<(Entity, &Transform, &mut Enemy), With<Enemy>>,
+    player_position: Vec2,
+    max_jumps: usize,
+    mut damage: f32,
+) -> bool {
+    let mut origin = player_position;
+    let mut radius = config.weapon.chain_lightning_range;
+    let mut struck: Vec<Entity> = Vec::new();
+
+    for _ in 0..max_jumps {
+        let Some((target, target_position)) = nearest_enemy(
+            enemy_query
+                .iter()
+                .filter(|(entity, _, _)| !struck.contains(entity))
+                .map(|(entity, transform, _)| (entity, transform.translation.truncate())),
+            origin,
+            radius,
+        ) else {
+            break;
+        };
+
+        if let Ok((_, _, mut enemy)) = enemy_query.get_mut(target) {
+            let hit = enemy.take_hit(Damaged::roll(
+                damage,
+                WeaponKind::ChainLightning,
+                &config.weapon,
+                crits,
+            ));
+            commands.trigger_targets(hit, target);
+        }
+        spawn_lightning_arc(
+            commands,
+            config,
+            origin,
+            target_position,
+            Color::srgb(0.7, 0.85, 1.0),
+        );
+
+        struck.push(target);
+        origin = target_position;
+        radius = config.weapon.chain_lightning_jump_range;
+        damage *= config.weapon.chain_lightning_falloff;
+    }
+
+    !struck.is_empty()
+}
+
+/// Strikes `targets` straight from the sky at full damage.
+fn call_thunderstorm(
+    commands: &mut Commands,
+    texture_handle: &GlobalTextureAtlas,
+    config: &Config,
+    crits: &mut impl Rng,
+    enemy_query: &mut Query<(Entity, &Transform, &mut Enemy), With<Enemy>>,
+    targets: &[(Entity, Vec2)],
+    damage: f32,
+) -> bool {
+    for (target, target_position) in targets.iter() {
+        if let Ok((_, _, mut enemy)) = enemy_query.get_mut(*target) {
+            let hit = enemy.take_hit(Damaged::roll(
+                damage,
+                WeaponKind::Thunderstorm,
+                &config.weapon,
+                crits,
+            ));
+            commands.trigger_targets(hit, *target);
+        }
+        spawn_lightning_arc(
+            commands,
+            config,
+            *target_position + vec2(0.0, 300.0),
+            *target_position,
+            Color::srgb(1.0, 0.9, 0.4),
+        );
+        commands.spawn((
+            LightningArc(Timer::from_seconds(
+                config.weapon.chain_lightning_arc_duration * 2.0,
+                TimerMode::Once,
+            )),
+            texture_handle.sprite("thunder_bolt"),
+            Transform::from_translation(target_position.extend(9.0))
+                .with_scale(Vec3::splat(config.sprite.sprite_scale_factor)),
+        ));
+    }
+
+    if !targets.is_empty() {
+        commands.trigger(ShakeCamera(config.camera.explosion_trauma));
+    }
+    !targets.is_empty()
+}
+
+fn spawn_lightning_arc(
+    commands: &mut Commands,
+    config: &Config,
+    from: Vec2,
+    to: Vec2,
+    color: Color,
+) {
+    let delta = to - from;
+    commands.spawn((
+        LightningArc(Timer::from_seconds(
+            config.weapon.chain_lightning_arc_duration,
+            TimerMode::Once,
+        )),
         Sprite {
-            image: texture_handle.image.clone().unwrap(),
-            texture_atlas: Some(TextureAtlas {
-                layout: texture_handle.layout.clone().unwrap(),
-                index: get_sprite_index(5, 1),
-            }),
+            color,
+            custom_size: Some(vec2(delta.length(), 3.0)),
             ..default()
         },
-        Transform::from_translation(vec3(weapon_position.x, weapon_position.y, 1.0))
-            .with_scale(Vec3::splat(CONFIG.sprite.sprite_scale_factor)),
-        ProjectileDirection(*projectile_direction),
+        Transform::from_translation(((from + to) / 2.0).extend(9.0))
+            .with_rotation(Quat::from_rotation_z(delta.to_angle())),
     ));
 }
 
+fn fade_lightning_arcs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut arc_query: Query<(&mut LightningArc, &mut Sprite, Entity), With<LightningArc>>,
+) {
+    for (mut arc, mut sprite, entity) in arc_query.iter_mut() {
+        arc.0.tick(time.delta());
+        if arc.0.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_alpha(arc.0.fraction_remaining());
+        }
+    }
+}
+
 fn update_projectiles(
+    mut commands: Commands,
     time: Res<Time>,
-    mut projectile_query: Query<(&mut Transform, &ProjectileDirection), With<Projectile>>,
+    config: Res<Config>,
+    mut projectile_query: Query<(Entity, &mut Transform, &ProjectileDirection, &Projectile)>,
 ) {
     if projectile_query.is_empty() {
         return;
     }
 
-    for (mut transform, direction) in projectile_query.iter_mut() {
-        transform.translation += direction.0.normalize_or_zero()
-            * Vec3::splat(CONFIG.player.projectile_speed * time.delta_secs());
+    let world = Rect::new(
+        -config.game.world_width,
+        -config.game.world_height,
+        config.game.world_width,
+        config.game.world_height,
+    );
+    for (entity, mut transform, direction, projectile) in projectile_query.iter_mut() {
+        transform.translation +=
+            direction.0.normalize_or_zero() * Vec3::splat(projectile.speed * time.delta_secs());
+        // a shot that missed everything is gone for good once it leaves the world
+        if !world.contains(transform.translation.truncate()) {
+            commands.entity(entity).despawn();
+        }
     }
 }
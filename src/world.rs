@@ -1,55 +1,49 @@
-use crate::config::CONFIG;
+use crate::config::Config;
 use crate::resources::GlobalTextureAtlas;
-use crate::state::GameState;
-use crate::util::get_sprite_index;
+use crate::rng::{seed_run, GameRng, RngStream};
+use crate::state::{run_scoped, GameState, InRun};
 use bevy::app::{App, Plugin};
 use bevy::math::{vec3, Vec3};
 use bevy::prelude::*;
 use rand::Rng;
-use std::thread::sleep;
-use std::time::Duration;
+
+#[derive(Component, Default)]
+#[require(StateScoped<InRun>(run_scoped))]
+pub struct Decoration;
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Initializing), decorate_world)
-            .add_systems(OnEnter(GameState::Dying), restart_game);
+        app.add_systems(
+            OnEnter(GameState::Initializing),
+            decorate_world.after(seed_run),
+        );
     }
 }
 
-fn decorate_world(mut commands: Commands, texture_handle: Res<GlobalTextureAtlas>) {
-    let mut rng = rand::rng();
+fn decorate_world(
+    mut commands: Commands,
+    config: Res<Config>,
+    texture_handle: Res<GlobalTextureAtlas>,
+    mut rng: ResMut<GameRng>,
+) {
+    let rng = rng.stream(RngStream::World);
 
-    for _ in 0..(CONFIG.game.world_height
-        * CONFIG.game.world_width
-        * CONFIG.game.decoration_density) as i32
+    for _ in 0..(config.game.world_height
+        * config.game.world_width
+        * config.game.decoration_density) as i32
     {
-        let x = rng.random_range(-CONFIG.game.world_width..CONFIG.game.world_width);
-        let y = rng.random_range(-CONFIG.game.world_height..CONFIG.game.world_height);
+        let x = rng.random_range(-config.game.world_width..config.game.world_width);
+        let y = rng.random_range(-config.game.world_height..config.game.world_height);
         commands.spawn((
-            Sprite {
-                image: texture_handle.image.clone().unwrap(),
-                texture_atlas: Some(TextureAtlas {
-                    layout: texture_handle.layout.clone().unwrap(),
-                    index: get_sprite_index(7, rng.random_range(0..8)),
-                }),
-                ..default()
-            },
+            Decoration,
+            texture_handle.sprite_variant(
+                "decoration",
+                rng.random_range(0..texture_handle.variants("decoration")),
+            ),
             Transform::from_translation(vec3(x, y, 0.0))
-                .with_scale(Vec3::splat(CONFIG.sprite.sprite_scale_factor)),
+                .with_scale(Vec3::splat(config.sprite.sprite_scale_factor)),
         ));
     }
 }
-
-fn restart_game(
-    mut commands: Commands,
-    query: Query<Entity, Without<bevy::window::PrimaryWindow>>,
-    mut next_state: ResMut<NextState<GameState>>,
-) {
-    sleep(Duration::from_secs(3));
-    for entity in query.iter() {
-        commands.entity(entity).despawn();
-    }
-    next_state.set(GameState::Loading);
-}
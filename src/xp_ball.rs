@@ -1,11 +1,14 @@
-use crate::config::CONFIG;
+use crate::audio::{PlaySound, SoundEffect};
+use crate::config::Config;
+use crate::game_over::RunStats;
+use crate::particles::ParticleEmitter;
 use crate::player::Player;
 use crate::resources::GlobalTextureAtlas;
-use crate::state::GameState;
-use crate::util::get_sprite_index;
+use crate::state::{run_scoped, GameState, InRun};
 use bevy::prelude::*;
 
 #[derive(Component, Default)]
+#[require(StateScoped<InRun>(run_scoped))]
 pub struct XPBall;
 
 impl XPBall {
@@ -13,19 +16,13 @@ impl XPBall {
         commands: &mut Commands,
         translation: Vec3,
         texture_handle: &Res<GlobalTextureAtlas>,
+        config: &Config,
     ) {
         commands.spawn((
             XPBall::default(),
-            Sprite {
-                image: texture_handle.image.clone().unwrap(),
-                texture_atlas: Some(TextureAtlas {
-                    layout: texture_handle.layout.clone().unwrap(),
-                    index: get_sprite_index(5, 2),
-                }),
-                ..default()
-            },
+            texture_handle.sprite("xp_orb"),
             Transform::from_translation(translation)
-                .with_scale(Vec3::splat(CONFIG.sprite.sprite_scale_factor)),
+                .with_scale(Vec3::splat(config.sprite.sprite_scale_factor)),
         ));
     }
 }
@@ -43,6 +40,7 @@ impl Plugin for XPBallPlugin {
 
 fn handle_player_pickup_xp(
     mut commands: Commands,
+    mut run_stats: ResMut<RunStats>,
     mut player_query: Query<(&Transform, &mut Player), With<Player>>,
     mut xp_ball_query: Query<(&Transform, Entity), With<XPBall>>,
 ) {
@@ -54,6 +52,12 @@ fn handle_player_pickup_xp(
                 <= 2500.0 * player.xp_ball_pickup_range_multiplier
             {
                 player.xp += 1;
+                run_stats.xp_collected += 1;
+                commands.trigger(PlaySound(SoundEffect::XpPickup));
+                commands.spawn((
+                    ParticleEmitter::xp_pickup(),
+                    Transform::from_translation(xp_ball_transform.translation),
+                ));
                 commands.entity(xp_ball_entity).despawn();
             }
         }